@@ -0,0 +1,29 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use my_evm::evm_with_gas;
+use std::hint::black_box;
+
+// PUSH3 100000, then the loop body from the JUMPDEST at 4 until the counter hits zero
+fn counting_loop(body: &str) -> Vec<u8> {
+    // PUSH1 1\nSWAP1\nSUB\nDUP1\nPUSH1 4\nJUMPI
+    hex::decode(format!("620186a05b{}600190038060045700", body)).unwrap()
+}
+
+fn loops(c: &mut Criterion) {
+    let mut group = c.benchmark_group("100000 iterations");
+
+    let code = counting_loop("");
+    group.bench_function("countdown", |b| {
+        b.iter(|| black_box(evm_with_gas(&code, u64::MAX)))
+    });
+
+    // DUP1\nDUP1\nMUL\nDUP2\nADD\nPUSH1 0x2a\nSWAP1\nMOD\nPOP
+    let code = counting_loop("8080028101602a900650");
+    group.bench_function("arithmetic", |b| {
+        b.iter(|| black_box(evm_with_gas(&code, u64::MAX)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, loops);
+criterion_main!(benches);
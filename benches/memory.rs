@@ -0,0 +1,106 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use my_evm::{evm, Memory};
+use primitive_types::U256;
+use std::collections::HashMap;
+use std::hint::black_box;
+
+// the memory we had before, one map entry per byte
+#[derive(Default)]
+struct HashMemory {
+    data: HashMap<U256, u8>,
+}
+
+impl HashMemory {
+    fn write(&mut self, offset: U256, bytes: &[u8]) {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.data.insert(offset + U256::from(i), *byte);
+        }
+    }
+
+    fn read(&self, mut offset: U256, size: U256) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32);
+        let end = offset + size;
+        while offset < end {
+            bytes.push(*self.data.get(&offset).unwrap_or(&0));
+            offset += 1.into();
+        }
+        bytes
+    }
+}
+
+const WORDS: usize = 1024;
+
+fn words(c: &mut Criterion) {
+    let word = [0xab; 32];
+    let mut group = c.benchmark_group("store and load 1024 words");
+
+    group.bench_function("hashmap", |b| {
+        b.iter(|| {
+            let mut memory = HashMemory::default();
+            for i in 0..WORDS {
+                memory.write((i * 32).into(), &word);
+            }
+            for i in 0..WORDS {
+                black_box(memory.read((i * 32).into(), 32.into()));
+            }
+        })
+    });
+
+    group.bench_function("vec", |b| {
+        b.iter(|| {
+            let mut memory = Memory::new();
+            memory.resize(WORDS * 32);
+            for i in 0..WORDS {
+                memory.set(i * 32, &word);
+            }
+            for i in 0..WORDS {
+                black_box(U256::from_big_endian(memory.slice(i * 32, 32)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+fn copy(c: &mut Criterion) {
+    let data = vec![0xcd; WORDS * 32];
+    let mut group = c.benchmark_group("copy 32 KiB");
+
+    group.bench_function("hashmap", |b| {
+        let mut memory = HashMemory::default();
+        memory.write(0.into(), &data);
+        b.iter(|| {
+            let bytes = memory.read(0.into(), data.len().into());
+            memory.write(data.len().into(), &bytes);
+        })
+    });
+
+    group.bench_function("vec", |b| {
+        let mut memory = Memory::new();
+        memory.resize(data.len() * 2);
+        memory.set(0, &data);
+        b.iter(|| memory.copy_within(0, data.len(), data.len()))
+    });
+
+    group.finish();
+}
+
+fn interpreter(c: &mut Criterion) {
+    // PUSH2 i\nPUSH2 i * 32\nMSTORE for every word, then hash all of it
+    let mut code = Vec::new();
+    for i in 0..WORDS as u16 {
+        code.push(0x61);
+        code.extend_from_slice(&i.to_be_bytes());
+        code.push(0x61);
+        code.extend_from_slice(&(i * 32).to_be_bytes());
+        code.push(0x52);
+    }
+    code.extend_from_slice(&[0x61, 0x80, 0x00, 0x60, 0x00, 0x20]);
+
+    c.bench_function("MSTORE 1024 words and SHA3 them", |b| {
+        b.iter(|| black_box(evm(&code)))
+    });
+}
+
+criterion_group!(benches, words, copy, interpreter);
+criterion_main!(benches);
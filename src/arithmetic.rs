@@ -1,4 +1,7 @@
-use crate::utils::Stack;
+use crate::gas::{self, Gas};
+use crate::spec::SpecId;
+use crate::stack::Stack;
+use crate::HaltReason;
 use primitive_types::{U256, U512};
 
 // arithmetic opcodes
@@ -14,9 +17,9 @@ const MULMOD: u8 = 0x09;
 const EXP: u8 = 0x0a;
 const SIGNEXTEND: u8 = 0x0b;
 
-// invert a signed U256
+// invert a signed U256, wrapping so that zero stays zero
 pub fn inv(val: U256) -> U256 {
-    !val + 1
+    (!val).overflowing_add(U256::one()).0
 }
 
 // check if the given number is negative
@@ -40,7 +43,6 @@ fn smod(mut left: U256, mut right: U256) -> U256 {
     // convert to positive representation if either value is negative
     if left_neg {
         left = inv(left);
-        println!("left {}", left);
     }
     if right_neg {
         right = inv(right);
@@ -49,8 +51,8 @@ fn smod(mut left: U256, mut right: U256) -> U256 {
     // postiive result
     let pos = left.checked_rem(right).unwrap_or(U256::zero());
 
-    // if signs are same, invert and then return
-    if left_neg && right_neg {
+    // the result takes the sign of the dividend
+    if left_neg {
         return inv(pos);
     }
     pos
@@ -87,23 +89,25 @@ fn sdiv(mut left: U256, mut right: U256) -> U256 {
     pos
 }
 
-fn sign_extend(stack: &mut Vec<U256>) {
+fn sign_extend(stack: &mut Stack) -> Result<(), HaltReason> {
     // number of bytes to read
-    let b = stack.safe_pop();
+    let b = stack.pop()?;
+
+    // value to extend
+    let x = stack.pop()?;
+
+    // extending from past the last byte leaves the value untouched
     if b > 31.into() {
-        panic!("reading more than max word size")
+        stack.push(x)?;
+        return Ok(());
     }
     let b = b.as_usize();
 
-    // value to extend
-    let x = stack.safe_pop();
     let mut bytes = [0; 32];
     x.to_little_endian(&mut bytes);
-    println!("{:x?}", bytes);
 
     // get sign bit
     let sign = (bytes[b] & 0b1000_0000) > 0;
-    println!("{}", sign);
 
     // if it's one, pad ones, else pad zeros
     if sign {
@@ -121,87 +125,93 @@ fn sign_extend(stack: &mut Vec<U256>) {
     }
 
     let res = U256::from_little_endian(&bytes);
-    stack.push(res);
+    stack.push(res)?;
+
+    Ok(())
 }
 
-pub fn exec(opcode: u8, stack: &mut Vec<U256>) {
+// reduce a 512 bit intermediate result back into a word
+fn truncate(val: U512) -> U256 {
+    let mut bytes = [0; 64];
+    val.to_little_endian(&mut bytes);
+
+    U256::from_little_endian(&bytes[0..32])
+}
+
+pub fn exec(opcode: u8, stack: &mut Stack, gas: &mut Gas, spec: SpecId) -> Result<(), HaltReason> {
     match opcode {
         ADD => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
             let (res, _) = left.overflowing_add(right);
-            stack.push(res);
+            stack.push(res)?;
         }
         MUL => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
             let (res, _) = left.overflowing_mul(right);
-            stack.push(res);
+            stack.push(res)?;
         }
         SUB => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
             let (res, _) = left.overflowing_sub(right);
-            stack.push(res);
+            stack.push(res)?;
         }
         DIV => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
             let res = left.checked_div(right).unwrap_or(U256::zero());
-            stack.push(res);
+            stack.push(res)?;
         }
         SDIV => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
 
-            stack.push(sdiv(left, right));
+            stack.push(sdiv(left, right))?;
         }
         MOD => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
             let res = left.checked_rem(right).unwrap_or(U256::zero());
-            stack.push(res);
+            stack.push(res)?;
         }
         SMOD => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
             let res = smod(left, right);
-            stack.push(res);
+            stack.push(res)?;
         }
         ADDMOD => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
-            let div = stack.pop().unwrap();
-            let res = left
-                .overflowing_add(right)
-                .0
+            let left = stack.pop()?;
+            let right = stack.pop()?;
+            let div = U512::from(stack.pop()?);
+            let res = (U512::from(left) + U512::from(right))
                 .checked_rem(div)
-                .unwrap_or(U256::zero());
-            stack.push(res)
+                .unwrap_or_default();
+            stack.push(truncate(res))?
         }
         MULMOD => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
-            let div = U512::from(stack.pop().unwrap());
-            let res = left.full_mul(right).checked_rem(div).unwrap();
-
-            let mut bytes = [0; 64];
-            res.to_little_endian(&mut bytes);
-
-            let res = U256::from_little_endian(&bytes[0..32]);
-            stack.push(res)
+            let left = stack.pop()?;
+            let right = stack.pop()?;
+            let div = U512::from(stack.pop()?);
+            let res = left.full_mul(right).checked_rem(div).unwrap_or_default();
+            stack.push(truncate(res))?
         }
         EXP => {
-            let base = stack.safe_pop();
-            let pow = stack.safe_pop();
+            let base = stack.pop()?;
+            let pow = stack.pop()?;
+            gas.record_cost(gas::exp_cost(pow.bits().div_ceil(8) as u64, spec))?;
             let res = base.overflowing_pow(pow).0;
-            stack.push(res);
+            stack.push(res)?;
         }
         SIGNEXTEND => {
-            sign_extend(stack);
+            sign_extend(stack)?;
         }
-        _ => panic!("Unrecognized arithmetic opcode"),
+        _ => unreachable!("Unrecognized arithmetic opcode"),
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -456,10 +466,17 @@ mod tests {
             TestSetup::new(
                 // wrapped
                 "PUSH1 2\nPUSH1 2\nPUSH32 MAX\nADDMOD",
-                "600260027fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff08",
+                "600260027fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff08",
                 vec!["0x01"],
                 true,
             ),
+            TestSetup::new(
+                // not subject to the 2^256 modulo
+                "PUSH32 MAX\nPUSH1 2\nPUSH32 MAX\nADDMOD",
+                "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff60027fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff08",
+                vec!["0x02"],
+                true,
+            ),
         ];
 
         for setup in setups {
@@ -480,10 +497,17 @@ mod tests {
              TestSetup::new(
                  // wrapped
                  "PUSH1 12\nPUSH32 MAX\nPUSH32 MAX\nMULMOD",
-                 "600c7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff09",
+                 "600c7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff09",
                  vec!["0x09"],
                  true,
              ),
+             TestSetup::new(
+                 // zero modulus
+                 "PUSH1 0\nPUSH1 10\nPUSH1 10\nMULMOD",
+                 "6000600a600a09",
+                 vec!["0x0"],
+                 true,
+             ),
          ];
 
         for setup in setups {
@@ -519,6 +543,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sign_extend_out_of_range() {
+        let setups = vec![TestSetup::new(
+            "PUSH1 0xff\nPUSH1 32\nSIGNEXTEND",
+            "60ff60200b",
+            vec!["0xff"],
+            true,
+        )];
+
+        for setup in setups {
+            run_test(setup);
+        }
+    }
+
     #[test]
     fn sdiv() {
         let setups = vec![
@@ -540,7 +578,19 @@ mod tests {
                 "60007ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd05",
                 vec!["0x0"],
                 true,
-            )
+            ),
+            TestSetup::new(
+                "PUSH1 2\nPUSH32 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\nSDIV",
+                "60027fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff05",
+                vec!["0x0"],
+                true,
+            ),
+            TestSetup::new(
+                "PUSH1 3\nPUSH32 0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff6\nSDIV",
+                "60037ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff605",
+                vec!["0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd"],
+                true,
+            ),
         ];
 
         for setup in setups {
@@ -563,7 +613,19 @@ mod tests {
                 "60007ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd07",
                 vec!["0x0"],
                 true,
-            )
+            ),
+            TestSetup::new(
+                "PUSH32 0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe\nPUSH32 0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc\nSMOD",
+                "7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc07",
+                vec!["0x0"],
+                true,
+            ),
+            TestSetup::new(
+                "PUSH1 3\nPUSH32 0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc\nSMOD",
+                "60037ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc07",
+                vec!["0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"],
+                true,
+            ),
         ];
 
         for setup in setups {
@@ -0,0 +1,188 @@
+use crate::env::address_to_word;
+use crate::stack::Stack;
+use crate::{Address, Env, HaltReason, DEFAULT_GAS_LIMIT};
+use primitive_types::{H256, U256};
+
+// block information opcodes
+
+const BLOCKHASH: u8 = 0x40;
+const COINBASE: u8 = 0x41;
+const TIMESTAMP: u8 = 0x42;
+const NUMBER: u8 = 0x43;
+const PREVRANDAO: u8 = 0x44;
+const GASLIMIT: u8 = 0x45;
+const CHAINID: u8 = 0x46;
+const BASEFEE: u8 = 0x48;
+const BLOBHASH: u8 = 0x49;
+const BLOBBASEFEE: u8 = 0x4a;
+
+// only this many of the most recent block hashes are available
+const BLOCKHASH_WINDOW: u64 = 256;
+
+/// the block a transaction is executed in
+#[derive(Debug, Clone)]
+pub struct BlockEnv {
+    pub coinbase: Address,
+    pub timestamp: U256,
+    pub number: u64,
+    pub prevrandao: H256,
+    pub gas_limit: u64,
+    pub chain_id: U256,
+    pub basefee: U256,
+    pub blob_basefee: U256,
+    /// hashes of the blocks before this one, oldest first, so the last entry is block `number - 1`
+    pub recent_hashes: Vec<H256>,
+}
+
+impl Default for BlockEnv {
+    fn default() -> Self {
+        Self {
+            coinbase: Address::zero(),
+            timestamp: U256::zero(),
+            number: 0,
+            prevrandao: H256::zero(),
+            gas_limit: DEFAULT_GAS_LIMIT,
+            chain_id: U256::one(),
+            basefee: U256::zero(),
+            blob_basefee: U256::one(),
+            recent_hashes: Vec::new(),
+        }
+    }
+}
+
+impl BlockEnv {
+    /// hash of block `number`, or zero if it isn't one of the last 256 blocks
+    pub fn block_hash(&self, number: U256) -> H256 {
+        if number >= self.number.into() {
+            return H256::zero();
+        }
+
+        let age = self.number - number.as_u64();
+        if age > BLOCKHASH_WINDOW || age as usize > self.recent_hashes.len() {
+            return H256::zero();
+        }
+
+        self.recent_hashes[self.recent_hashes.len() - age as usize]
+    }
+}
+
+pub fn exec(opcode: u8, stack: &mut Stack, env: &Env) -> Result<(), HaltReason> {
+    let block = &env.block;
+    match opcode {
+        BLOCKHASH => {
+            let number = stack.pop()?;
+            stack.push(U256::from_big_endian(block.block_hash(number).as_bytes()))?;
+        }
+        COINBASE => stack.push(address_to_word(block.coinbase))?,
+        TIMESTAMP => stack.push(block.timestamp)?,
+        NUMBER => stack.push(block.number.into())?,
+        PREVRANDAO => stack.push(U256::from_big_endian(block.prevrandao.as_bytes()))?,
+        GASLIMIT => stack.push(block.gas_limit.into())?,
+        CHAINID => stack.push(block.chain_id)?,
+        BASEFEE => stack.push(block.basefee)?,
+        BLOBHASH => {
+            let index = stack.pop()?;
+            let hash = if index < env.blob_hashes.len().into() {
+                env.blob_hashes[index.as_usize()]
+            } else {
+                H256::zero()
+            };
+            stack.push(U256::from_big_endian(hash.as_bytes()))?;
+        }
+        BLOBBASEFEE => stack.push(block.blob_basefee)?,
+        _ => unreachable!("Not a block opcode"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockEnv;
+    use crate::{execute, Address, Env, InMemoryDb};
+    use primitive_types::{H256, U256};
+
+    fn run(bin: &str, env: &Env) -> Vec<U256> {
+        let mut storage = InMemoryDb::new();
+        let result = execute(hex::decode(bin).unwrap(), env, &mut storage);
+        assert!(result.success, "{}", bin);
+        result.stack
+    }
+
+    fn test_env() -> Env {
+        Env {
+            block: BlockEnv {
+                coinbase: Address::from_low_u64_be(0xc014),
+                timestamp: 1_700_000_000.into(),
+                number: 1000,
+                prevrandao: H256::from_low_u64_be(0x4a4d),
+                gas_limit: 30_000_000,
+                chain_id: 5.into(),
+                basefee: 10.into(),
+                blob_basefee: 3.into(),
+                recent_hashes: (0..300).map(|i| H256::from_low_u64_be(700 + i)).collect(),
+            },
+            blob_hashes: vec![H256::from_low_u64_be(0xb10b)],
+            ..Env::default()
+        }
+    }
+
+    #[test]
+    fn block_info() {
+        let env = test_env();
+        // COINBASE\nTIMESTAMP\nNUMBER\nPREVRANDAO\nGASLIMIT\nCHAINID\nBASEFEE\nBLOBBASEFEE
+        assert_eq!(
+            run("414243444546484a", &env),
+            vec![
+                0xc014.into(),
+                1_700_000_000.into(),
+                1000.into(),
+                0x4a4d.into(),
+                30_000_000.into(),
+                5.into(),
+                10.into(),
+                3.into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn blockhash() {
+        let env = test_env();
+
+        // hashes are only available for the 256 blocks before this one
+        // PUSH2 999\nBLOCKHASH\nPUSH2 744\nBLOCKHASH\nPUSH2 743\nBLOCKHASH
+        assert_eq!(
+            run("6103e7406102e8406102e740", &env),
+            vec![999.into(), 744.into(), 0.into()]
+        );
+
+        // PUSH2 1000\nBLOCKHASH\nPUSH32 MAX\nBLOCKHASH
+        assert_eq!(
+            run(
+                "6103e8407fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff40",
+                &env
+            ),
+            vec![0.into(), 0.into()]
+        );
+
+        // not enough hashes supplied
+        let env = Env {
+            block: BlockEnv {
+                number: 1000,
+                recent_hashes: vec![H256::from_low_u64_be(999)],
+                ..BlockEnv::default()
+            },
+            ..Env::default()
+        };
+        // PUSH2 999\nBLOCKHASH\nPUSH2 998\nBLOCKHASH
+        assert_eq!(run("6103e7406103e640", &env), vec![999.into(), 0.into()]);
+    }
+
+    #[test]
+    fn blobhash() {
+        let env = test_env();
+        // PUSH1 0\nBLOBHASH\nPUSH1 1\nBLOBHASH
+        assert_eq!(run("600049600149", &env), vec![0xb10b.into(), 0.into()]);
+    }
+}
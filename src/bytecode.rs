@@ -0,0 +1,186 @@
+use crate::{PUSH1, PUSH32, STOP};
+use primitive_types::H256;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const JUMPDEST: u8 = 0x5b;
+
+// enough trailing STOPs for a PUSH32 in the last byte to read zeros and then halt
+const PADDING: usize = 33;
+
+/// contract code with its jump destinations worked out ahead of running it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnalyzedBytecode {
+    // the code followed by `PADDING` STOPs
+    code: Vec<u8>,
+    len: usize,
+    // bit `i % 64` of word `i / 64` is set when byte `i` is a JUMPDEST opcode
+    jumps: Vec<u64>,
+}
+
+impl AnalyzedBytecode {
+    pub fn new(mut code: Vec<u8>) -> Self {
+        let len = code.len();
+        let mut jumps = vec![0u64; len.div_ceil(64)];
+
+        let mut i = 0;
+        while i < len {
+            let op = code[i];
+            if op == JUMPDEST {
+                jumps[i / 64] |= 1 << (i % 64);
+            } else if (PUSH1..=PUSH32).contains(&op) {
+                // push data is never an opcode
+                i += (op - PUSH1 + 1) as usize;
+            }
+            i += 1;
+        }
+
+        code.resize(len + PADDING, STOP);
+        Self { code, len, jumps }
+    }
+
+    /// the code as deployed, without the padding
+    pub fn code(&self) -> &[u8] {
+        &self.code[..self.len]
+    }
+
+    /// the code followed by enough `STOP`s that an instruction never reads past the end
+    pub fn padded_code(&self) -> &[u8] {
+        &self.code
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// whether `dest` is a `JUMPDEST` opcode rather than push data or past the end
+    pub fn is_jumpdest(&self, dest: usize) -> bool {
+        self.jumps
+            .get(dest / 64)
+            .is_some_and(|word| (word >> (dest % 64)) & 1 == 1)
+    }
+}
+
+/// analyzed code keyed by its hash, so calling into the same contract again skips analysis
+#[derive(Debug, Default)]
+pub struct BytecodeCache {
+    entries: HashMap<H256, Rc<AnalyzedBytecode>>,
+}
+
+impl BytecodeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the analysis of the code hashing to `hash`, only fetching the code when it's not cached
+    pub fn get_or_analyze(
+        &mut self,
+        hash: H256,
+        code: impl FnOnce() -> Vec<u8>,
+    ) -> Rc<AnalyzedBytecode> {
+        self.entries
+            .entry(hash)
+            .or_insert_with(|| Rc::new(AnalyzedBytecode::new(code())))
+            .clone()
+    }
+
+    /// number of distinct pieces of code analyzed
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnalyzedBytecode, BytecodeCache};
+    use crate::state::code_hash;
+    use crate::{execute_with_cache, transact_with_cache, Address, Database, Env, InMemoryDb};
+    use std::rc::Rc;
+
+    #[test]
+    fn jumpdests() {
+        // JUMPDEST\nPUSH1 0x5b\nJUMPDEST\nPUSH2 0x5b5b
+        let code = AnalyzedBytecode::new(hex::decode("5b605b5b615b5b").unwrap());
+        let jumps: Vec<usize> = (0..10).filter(|&i| code.is_jumpdest(i)).collect();
+        assert_eq!(jumps, vec![0, 3]);
+
+        // destinations past the first word of the bitmap
+        let mut bin = vec![0x5b; 130];
+        bin[64] = 0x00;
+        let code = AnalyzedBytecode::new(bin);
+        assert!(code.is_jumpdest(63));
+        assert!(!code.is_jumpdest(64));
+        assert!(code.is_jumpdest(65));
+        assert!(code.is_jumpdest(129));
+        assert!(!code.is_jumpdest(130));
+        assert!(!code.is_jumpdest(usize::MAX));
+    }
+
+    #[test]
+    fn padding() {
+        // PUSH32 with only one byte of data
+        let code = AnalyzedBytecode::new(hex::decode("7f01").unwrap());
+        assert_eq!(code.len(), 2);
+        assert_eq!(code.code(), hex::decode("7f01").unwrap());
+
+        // the push data reads as zeros and is followed by a STOP
+        let padded = code.padded_code();
+        assert_eq!(padded.len(), 2 + 33);
+        assert!(padded[2..].iter().all(|&byte| byte == 0));
+
+        let code = AnalyzedBytecode::new(Vec::new());
+        assert!(code.is_empty());
+        assert_eq!(code.padded_code(), [0; 33]);
+    }
+
+    #[test]
+    fn cache() {
+        let bin = hex::decode("60015b00").unwrap();
+        let mut cache = BytecodeCache::new();
+
+        let first = cache.get_or_analyze(code_hash(&bin), || bin.clone());
+        assert!(first.is_jumpdest(2));
+
+        // the second lookup never needs the code
+        let second = cache.get_or_analyze(code_hash(&bin), || unreachable!());
+        assert!(Rc::ptr_eq(&first, &second));
+
+        let empty = cache.get_or_analyze(code_hash(&[]), Vec::new);
+        assert!(!Rc::ptr_eq(&first, &empty));
+    }
+
+    #[test]
+    fn reused_between_transactions() {
+        // 0xc0de calls 0xbeef, which runs PUSH1 0x2a
+        let bin = "6000600060006000600061beef5af1";
+        let mut db = InMemoryDb::new();
+        db.set_code(Address::from_low_u64_be(0xc0de), hex::decode(bin).unwrap());
+        db.set_code(
+            Address::from_low_u64_be(0xbeef),
+            hex::decode("602a").unwrap(),
+        );
+        let env = Env {
+            address: Address::from_low_u64_be(0xc0de),
+            ..Env::default()
+        };
+
+        let mut cache = BytecodeCache::new();
+        assert!(cache.is_empty());
+        let first = transact_with_cache(&env, &mut db, &mut cache);
+        assert_eq!(cache.len(), 2);
+
+        // running the same code again finds both contracts already analyzed
+        let second = transact_with_cache(&env, &mut db, &mut cache);
+        assert_eq!(second.stack, first.stack);
+        execute_with_cache(hex::decode(bin).unwrap(), &env, &mut db, &mut cache);
+        assert_eq!(cache.len(), 2);
+    }
+}
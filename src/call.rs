@@ -0,0 +1,413 @@
+use crate::env::{word_to_address, CallContext};
+use crate::gas::{self, Gas};
+use crate::journal::Journal;
+use crate::memory::{expand, mload_n, write};
+use crate::spec::SpecId;
+use crate::stack::Stack;
+use crate::state::access_account;
+use crate::{Address, HaltReason, Memory};
+use primitive_types::U256;
+
+// call opcodes
+
+const CALL: u8 = 0xf1;
+const CALLCODE: u8 = 0xf2;
+const DELEGATECALL: u8 = 0xf4;
+const STATICCALL: u8 = 0xfa;
+
+/// calls can't be nested any deeper than this
+pub const CALL_DEPTH_LIMIT: usize = 1024;
+
+/// a sub call requested by one of the call opcodes
+pub struct CallRequest {
+    /// account whose code should run, or the new account for creations
+    pub code_address: Address,
+    /// code to run instead of loading it, set for creations
+    pub init_code: Option<Vec<u8>>,
+    pub context: CallContext,
+    /// wei moved from `context.caller` to `context.address` before the code runs
+    pub transfer: U256,
+    pub gas_limit: u64,
+    /// region of the caller's memory that the output is copied into
+    pub ret_offset: U256,
+    pub ret_size: U256,
+}
+
+impl CallRequest {
+    /// the outcome of ending this call without running any code
+    pub fn outcome(&self, reason: HaltReason, output: Vec<u8>, gas_left: u64) -> FrameOutcome {
+        FrameOutcome {
+            reason,
+            output,
+            gas_left,
+            created: self.init_code.is_some().then_some(self.code_address),
+            ret_offset: self.ret_offset,
+            ret_size: self.ret_size,
+        }
+    }
+}
+
+/// how a sub call or creation ended, to be handed back to the frame which made it
+pub struct FrameOutcome {
+    pub reason: HaltReason,
+    pub output: Vec<u8>,
+    pub gas_left: u64,
+    /// the new account for creations
+    pub created: Option<Address>,
+    /// region of the caller's memory that the output is copied into
+    pub ret_offset: U256,
+    pub ret_size: U256,
+}
+
+pub fn exec(
+    opcode: u8,
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+    context: &CallContext,
+    journal: &mut Journal,
+    spec: SpecId,
+) -> Result<CallRequest, HaltReason> {
+    let gas_requested = stack.pop()?;
+    let target = word_to_address(stack.pop()?);
+    let value = match opcode {
+        CALL | CALLCODE => stack.pop()?,
+        _ => U256::zero(),
+    };
+    let args_offset = stack.pop()?;
+    let args_size = stack.pop()?;
+    let ret_offset = stack.pop()?;
+    let ret_size = stack.pop()?;
+
+    if opcode == CALL && context.is_static && !value.is_zero() {
+        return Err(HaltReason::StateChangeDuringStaticCall);
+    }
+
+    let calldata = mload_n(memory, gas, args_offset, args_size)?.to_vec();
+    expand(memory, gas, ret_offset, ret_size)?;
+    access_account(journal, gas, target, spec)?;
+
+    if !value.is_zero() {
+        gas.record_cost(gas::CALL_VALUE)?;
+    }
+
+    if opcode == CALL {
+        gas.record_cost(gas::new_account_cost(
+            spec,
+            !value.is_zero(),
+            journal.is_empty(target),
+            true,
+        ))?;
+    }
+
+    // before EIP-150 asking for more than what's left ran out of gas
+    let available = if spec.is_enabled_in(SpecId::TangerineWhistle) {
+        gas.all_but_one_64th()
+    } else {
+        u64::MAX
+    };
+    let mut gas_limit = if gas_requested > available.into() {
+        available
+    } else {
+        gas_requested.as_u64()
+    };
+    gas.record_cost(gas_limit)?;
+
+    // the callee gets a little extra for free when receiving value
+    if !value.is_zero() {
+        gas_limit += gas::CALL_STIPEND;
+    }
+
+    let context = match opcode {
+        CALL => CallContext {
+            caller: context.address,
+            address: target,
+            value,
+            calldata,
+            is_static: context.is_static,
+        },
+        // run the target's code against our own storage
+        CALLCODE => CallContext {
+            caller: context.address,
+            address: context.address,
+            value,
+            calldata,
+            is_static: context.is_static,
+        },
+        // like CALLCODE, but keeping our caller and value too
+        DELEGATECALL => CallContext {
+            caller: context.caller,
+            address: context.address,
+            value: context.value,
+            calldata,
+            is_static: context.is_static,
+        },
+        STATICCALL => CallContext {
+            caller: context.address,
+            address: target,
+            value: U256::zero(),
+            calldata,
+            is_static: true,
+        },
+        _ => unreachable!("Not a call opcode"),
+    };
+
+    Ok(CallRequest {
+        code_address: target,
+        init_code: None,
+        context,
+        transfer: value,
+        gas_limit,
+        ret_offset,
+        ret_size,
+    })
+}
+
+/// hand the outcome of a finished sub call back to the caller
+pub fn finish(
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+    return_data: &mut Vec<u8>,
+    outcome: FrameOutcome,
+) {
+    gas.return_gas(outcome.gas_left);
+
+    // the output region was already paid for when making the call
+    let size = outcome.output.len().min(outcome.ret_size.as_usize());
+    write(memory, outcome.ret_offset, &outcome.output[..size]);
+    *return_data = outcome.output;
+
+    // the call's arguments were popped, so there's always room for the result
+    let success = outcome.reason.is_success();
+    stack
+        .push(if success { U256::one() } else { U256::zero() })
+        .expect("no room for the call result");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{run_with_callee, test_env, CALLEE};
+    use crate::{execute, Address, Database, Env, HaltReason, InMemoryDb, SpecId};
+    use primitive_types::U256;
+
+    fn env() -> Env {
+        Env {
+            caller: Address::from_low_u64_be(0xca11),
+            value: 7.into(),
+            ..test_env()
+        }
+    }
+
+    #[test]
+    fn call_returns_output() {
+        let mut db = InMemoryDb::new();
+
+        // callee: PUSH1 0x2a\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nRETURN
+        // caller: CALL 0xbeef with 32 bytes of output at 0\nPUSH1 0\nMLOAD\nRETURNDATASIZE
+        let result = run_with_callee(
+            "6020600060006000600061beef5af16000513d",
+            "602a60005260206000f3",
+            &env(),
+            &mut db,
+        );
+        assert!(result.success);
+        assert_eq!(result.stack, vec![1.into(), 0x2a.into(), 32.into()]);
+    }
+
+    #[test]
+    fn call_context() {
+        let mut db = InMemoryDb::new();
+        let env = env();
+        db.set_balance(env.address, 100.into());
+
+        // callee: CALLER\nPUSH1 0\nMSTORE\nCALLVALUE\nPUSH1 32\nMSTORE\nPUSH1 64\nPUSH1 0\nRETURN
+        let callee = "336000523460205260406000f3";
+
+        // CALL 0xbeef with a value of 5\nPUSH1 0\nMLOAD\nPUSH1 32\nMLOAD
+        let result = run_with_callee(
+            "6040600060006000600561beef5af1600051602051",
+            callee,
+            &env,
+            &mut db,
+        );
+        assert_eq!(result.stack, vec![1.into(), 0xc0de.into(), 5.into()]);
+
+        // DELEGATECALL keeps our caller and value
+        let result = run_with_callee(
+            "604060006000600061beef5af4600051602051",
+            callee,
+            &env,
+            &mut db,
+        );
+        assert_eq!(result.stack, vec![1.into(), 0xca11.into(), 7.into()]);
+    }
+
+    #[test]
+    fn storage_context() {
+        let env = env();
+        let caller = env.address;
+        let callee = Address::from_low_u64_be(CALLEE);
+
+        // PUSH1 0x2a\nPUSH1 0\nSSTORE
+        let store = "602a600055";
+
+        // CALL writes to the callee's storage
+        let mut db = InMemoryDb::new();
+        let result = run_with_callee("6000600060006000600061beef5af1", store, &env, &mut db);
+        assert_eq!(result.stack, vec![1.into()]);
+        assert_eq!(db.sload(callee, 0.into()), 0x2a.into());
+        assert_eq!(db.sload(caller, 0.into()), 0.into());
+
+        // CALLCODE and DELEGATECALL write to ours
+        let mut db = InMemoryDb::new();
+        let result = run_with_callee("6000600060006000600061beef5af2", store, &env, &mut db);
+        assert_eq!(result.stack, vec![1.into()]);
+        assert_eq!(db.sload(callee, 0.into()), 0.into());
+        assert_eq!(db.sload(caller, 0.into()), 0x2a.into());
+
+        let mut db = InMemoryDb::new();
+        let result = run_with_callee("600060006000600061beef5af4", store, &env, &mut db);
+        assert_eq!(result.stack, vec![1.into()]);
+        assert_eq!(db.sload(callee, 0.into()), 0.into());
+        assert_eq!(db.sload(caller, 0.into()), 0x2a.into());
+    }
+
+    #[test]
+    fn staticcall() {
+        let mut db = InMemoryDb::new();
+
+        // STATICCALL 0xbeef, which tries to SSTORE
+        let result = run_with_callee("600060006000600061beef5afa", "602a600055", &env(), &mut db);
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into()]);
+        assert_eq!(
+            db.sload(Address::from_low_u64_be(CALLEE), 0.into()),
+            0.into()
+        );
+
+        // sending value is a state change too
+        // 0xbeef: CALL 0xbeef with a value of 1
+        let result = run_with_callee(
+            "600060006000600061beef5afa",
+            "6000600060006000600161beef5af1",
+            &env(),
+            &mut db,
+        );
+        assert_eq!(result.stack, vec![0.into()]);
+
+        // reading is fine
+        let result = run_with_callee(
+            "602060006000600061beef5afa600051",
+            "602a60005260206000f3",
+            &env(),
+            &mut db,
+        );
+        assert_eq!(result.stack, vec![1.into(), 0x2a.into()]);
+    }
+
+    #[test]
+    fn revert() {
+        let mut db = InMemoryDb::new();
+
+        // callee: PUSH1 0x2a\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nREVERT
+        let result = run_with_callee(
+            "6020600060006000600061beef5af16000513d",
+            "602a60005260206000fd",
+            &env(),
+            &mut db,
+        );
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into(), 0x2a.into(), 32.into()]);
+    }
+
+    #[test]
+    fn new_account() {
+        let run = |bin: &str, spec: SpecId| {
+            let mut db = InMemoryDb::new();
+            let env = Env { spec, ..env() };
+            db.set_balance(env.address, 100.into());
+            execute(hex::decode(bin).unwrap(), &env, &mut db)
+        };
+
+        // CALL 0xf000 with no gas and a value of 1
+        let send = "6000600060006000600161f0006000f1";
+        let result = run(send, SpecId::Cancun);
+        assert_eq!(result.stack, vec![1.into()]);
+        // 7 pushes, the cold call, the value and the new account, less the unused stipend
+        assert_eq!(result.gas_used, 7 * 3 + 2600 + 9000 + 25000 - 2300);
+
+        // CALL 0xf000 with no gas and no value
+        let call = "6000600060006000600061f0006000f1";
+        assert_eq!(run(call, SpecId::Cancun).gas_used, 7 * 3 + 2600);
+
+        // before EIP-161 the account was charged for even without value
+        assert_eq!(run(call, SpecId::Homestead).gas_used, 7 * 3 + 40 + 25000);
+        assert_eq!(run(call, SpecId::SpuriousDragon).gas_used, 7 * 3 + 700);
+    }
+
+    #[test]
+    fn all_but_one_64th() {
+        let mut db = InMemoryDb::new();
+
+        // callee: GAS\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nRETURN
+        // caller: CALL 0xbeef asking for all the gas\nPUSH1 0\nMLOAD
+        let result = run_with_callee(
+            "6020600060006000600061beef7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff1600051",
+            "5a60005260206000f3",
+            &env(),
+            &mut db,
+        );
+
+        // 7 pushes, the cold call and one word of memory for the output
+        let remaining = 1_000_000 - 7 * 3 - 2600 - 3;
+        let forwarded = remaining - remaining / 64;
+        assert_eq!(result.stack, vec![1.into(), U256::from(forwarded - 2)]);
+    }
+
+    #[test]
+    fn depth_limit() {
+        let mut db = InMemoryDb::new();
+        let address = Address::from_low_u64_be(0xd);
+
+        // PUSH1 0\nSLOAD\nPUSH1 1\nADD\nPUSH1 0\nSSTORE
+        // CALL ADDRESS with all the gas
+        db.set_code(
+            address,
+            hex::decode("60005460010160005560006000600060006000305af1").unwrap(),
+        );
+        let env = Env {
+            address,
+            gas_limit: 1_000_000_000_000_000,
+            ..Env::default()
+        };
+        let result = crate::transact(&env, &mut db);
+        assert!(result.success);
+
+        // the outermost frame plus 1024 nested calls
+        assert_eq!(db.sload(address, 0.into()), 1025.into());
+    }
+
+    #[test]
+    fn return_data_copy() {
+        let mut db = InMemoryDb::new();
+
+        // PUSH1 32\nPUSH1 0\nPUSH1 0\nRETURNDATACOPY\nPUSH1 0\nMLOAD
+        let result = run_with_callee(
+            "6000600060006000600061beef5af16020600060003e600051",
+            "602a60005260206000f3",
+            &env(),
+            &mut db,
+        );
+        assert_eq!(result.stack, vec![1.into(), 0x2a.into()]);
+
+        // PUSH1 33\nPUSH1 0\nPUSH1 0\nRETURNDATACOPY
+        let result = run_with_callee(
+            "6000600060006000600061beef5af16021600060003e",
+            "602a60005260206000f3",
+            &env(),
+            &mut db,
+        );
+        assert_eq!(result.reason, HaltReason::ReturnDataOutOfBounds);
+    }
+}
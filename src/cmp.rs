@@ -1,5 +1,6 @@
-use crate::arithmetic::is_negative;
-use crate::utils::Stack;
+use crate::arithmetic::{inv, is_negative};
+use crate::stack::Stack;
+use crate::HaltReason;
 use primitive_types::U256;
 
 const LT: u8 = 0x10;
@@ -26,127 +27,154 @@ fn sar(mut left: U256, right: U256) -> U256 {
     if is_negative(left) {
         // negative
         if right >= U256::from(256) {
-            return U256::MAX;
+            U256::MAX
         } else {
             let right: usize = right.as_usize();
             left >>= right;
             // set all the bits less than `right` to one
             left |= U256::MAX << right;
 
-            return left;
+            left
         }
     } else {
         // postive
         if right >= U256::from(256) {
-            return U256::zero();
+            U256::zero()
         } else {
             let right: usize = right.as_usize();
-            return left >> right;
+            left >> right
         }
     }
 }
 
-pub fn exec(opcode: u8, stack: &mut Vec<U256>) {
+// signed less than for two's complement values
+fn slt(left: U256, right: U256) -> bool {
+    match (is_negative(left), is_negative(right)) {
+        (true, false) => true,
+        (false, true) => false,
+        // both negative, so the larger magnitude is the smaller number
+        (true, true) => inv(left) > inv(right),
+        (false, false) => left < right,
+    }
+}
+
+pub fn exec(opcode: u8, stack: &mut Stack) -> Result<(), HaltReason> {
     match opcode {
         LT => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
             if left < right {
-                stack.push(U256::one());
+                stack.push(U256::one())?;
             } else {
-                stack.push(U256::zero());
+                stack.push(U256::zero())?;
             }
         }
         GT => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
             if left > right {
-                stack.push(U256::one());
+                stack.push(U256::one())?;
             } else {
-                stack.push(U256::zero());
+                stack.push(U256::zero())?;
+            }
+        }
+        SLT => {
+            let left = stack.pop()?;
+            let right = stack.pop()?;
+            if slt(left, right) {
+                stack.push(U256::one())?;
+            } else {
+                stack.push(U256::zero())?;
+            }
+        }
+        SGT => {
+            let left = stack.pop()?;
+            let right = stack.pop()?;
+            if slt(right, left) {
+                stack.push(U256::one())?;
+            } else {
+                stack.push(U256::zero())?;
             }
         }
-        SLT => todo!(),
-        SGT => todo!(),
         EQ => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
+            let left = stack.pop()?;
+            let right = stack.pop()?;
             if left == right {
-                stack.push(U256::one());
+                stack.push(U256::one())?;
             } else {
-                stack.push(U256::zero());
+                stack.push(U256::zero())?;
             }
         }
         ISZERO => {
-            let left = stack.safe_pop();
+            let left = stack.pop()?;
             if left == U256::zero() {
-                stack.push(U256::one());
+                stack.push(U256::one())?;
             } else {
-                stack.push(U256::zero());
+                stack.push(U256::zero())?;
             }
         }
         AND => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
-            stack.push(left & right);
+            let left = stack.pop()?;
+            let right = stack.pop()?;
+            stack.push(left & right)?;
         }
         OR => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
-            stack.push(left | right);
+            let left = stack.pop()?;
+            let right = stack.pop()?;
+            stack.push(left | right)?;
         }
         XOR => {
-            let left = stack.safe_pop();
-            let right = stack.safe_pop();
-            stack.push(left ^ right);
+            let left = stack.pop()?;
+            let right = stack.pop()?;
+            stack.push(left ^ right)?;
         }
         NOT => {
-            let left = stack.safe_pop();
-            stack.push(!left);
+            let left = stack.pop()?;
+            stack.push(!left)?;
         }
         BYTE => {
-            let offset = stack.safe_pop();
-            let value = stack.safe_pop();
-
-            let offset = offset.as_usize();
+            let offset = stack.pop()?;
+            let value = stack.pop()?;
 
-            if offset > 31 || value == U256::zero() {
-                stack.push(U256::zero());
+            if offset > 31.into() || value == U256::zero() {
+                stack.push(U256::zero())?;
             } else {
-                let byte = value.byte(31 - offset);
-                stack.push(U256::from(byte));
+                let byte = value.byte(31 - offset.as_usize());
+                stack.push(U256::from(byte))?;
             }
         }
         SHL => {
-            let right = stack.safe_pop();
-            let left = stack.safe_pop();
+            let right = stack.pop()?;
+            let left = stack.pop()?;
             if right >= U256::from(256) || left == U256::zero() {
-                stack.push(U256::zero());
+                stack.push(U256::zero())?;
             } else {
                 let right: u64 = right.as_u64();
                 let shifted = left << right as usize;
-                stack.push(shifted);
+                stack.push(shifted)?;
             }
         }
         SHR => {
-            let right = stack.safe_pop();
-            let left = stack.safe_pop();
+            let right = stack.pop()?;
+            let left = stack.pop()?;
             if right >= U256::from(256) || left == U256::zero() {
-                stack.push(U256::zero());
+                stack.push(U256::zero())?;
             } else {
                 let right: u64 = right.as_u64();
                 let shifted = left >> right as usize;
-                stack.push(shifted);
+                stack.push(shifted)?;
             }
         }
         SAR => {
-            let right = stack.safe_pop();
-            let left = stack.safe_pop();
+            let right = stack.pop()?;
+            let left = stack.pop()?;
             let res = sar(left, right);
-            stack.push(res);
+            stack.push(res)?;
         }
-        _ => panic!("Not a cmp or bitwise opcode!"),
+        _ => unreachable!("Not a comparison opcode"),
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -179,6 +207,81 @@ mod tests {
         }
     }
 
+    #[test]
+    fn signed_less_than() {
+        let setups = vec![
+            TestSetup::new("PUSH1 10\nPUSH1 9\nSLT", "600a600912", vec!["0x1"], true),
+            TestSetup::new("PUSH1 9\nPUSH1 10\nSLT", "6009600a12", vec!["0x0"], true),
+            TestSetup::new("PUSH1 9\nPUSH1 9\nSLT", "6009600912", vec!["0x0"], true),
+            TestSetup::new(
+                // -1 < 0
+                "PUSH1 0\nPUSH32 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\nSLT",
+                "60007fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff12",
+                vec!["0x1"],
+                true,
+            ),
+            TestSetup::new(
+                // 0 < -1
+                "PUSH32 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\nPUSH1 0\nSLT",
+                "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff600012",
+                vec!["0x0"],
+                true,
+            ),
+            TestSetup::new(
+                // -3 < -2
+                "PUSH32 0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe\nPUSH32 0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd\nSLT",
+                "7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd12",
+                vec!["0x1"],
+                true,
+            ),
+            TestSetup::new(
+                // min < max
+                "PUSH32 0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\nPUSH32 0x8000000000000000000000000000000000000000000000000000000000000000\nSLT",
+                "7f7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f800000000000000000000000000000000000000000000000000000000000000012",
+                vec!["0x1"],
+                true,
+            ),
+        ];
+
+        for setup in setups {
+            run_test(setup);
+        }
+    }
+
+    #[test]
+    fn signed_greater_than() {
+        let setups = vec![
+            TestSetup::new("PUSH1 10\nPUSH1 9\nSGT", "600a600913", vec!["0x0"], true),
+            TestSetup::new("PUSH1 9\nPUSH1 10\nSGT", "6009600a13", vec!["0x1"], true),
+            TestSetup::new("PUSH1 9\nPUSH1 9\nSGT", "6009600913", vec!["0x0"], true),
+            TestSetup::new(
+                // -2 > -3
+                "PUSH32 0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd\nPUSH32 0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe\nSGT",
+                "7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffd7ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe13",
+                vec!["0x1"],
+                true,
+            ),
+            TestSetup::new(
+                // 0 > -1
+                "PUSH32 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\nPUSH1 0\nSGT",
+                "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff600013",
+                vec!["0x1"],
+                true,
+            ),
+            TestSetup::new(
+                // -1 > 0
+                "PUSH1 0\nPUSH32 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\nSGT",
+                "60007fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff13",
+                vec!["0x0"],
+                true,
+            ),
+        ];
+
+        for setup in setups {
+            run_test(setup);
+        }
+    }
+
     #[test]
     fn equals() {
         let setups = vec![
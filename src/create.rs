@@ -0,0 +1,421 @@
+use crate::call::{CallRequest, FrameOutcome};
+use crate::env::{address_to_word, CallContext};
+use crate::gas::{self, Gas};
+use crate::hash::sha3;
+use crate::memory::mload_n;
+use crate::spec::SpecId;
+use crate::stack::Stack;
+use crate::{Address, HaltReason, Memory};
+use primitive_types::U256;
+
+// contract creation opcodes
+
+const CREATE: u8 = 0xf0;
+const CREATE2: u8 = 0xf5;
+
+/// largest runtime code that can be deployed (EIP-170)
+pub const MAX_CODE_SIZE: usize = 0x6000;
+/// largest init code that can be run (EIP-3860)
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+// rlp encoding of an integer, as used for nonces
+fn rlp_u64(value: u64) -> Vec<u8> {
+    match value {
+        0 => vec![0x80],
+        1..=0x7f => vec![value as u8],
+        _ => {
+            let bytes = value.to_be_bytes();
+            let start = bytes.iter().position(|b| *b != 0).unwrap_or(0);
+            let mut encoded = vec![0x80 + (8 - start) as u8];
+            encoded.extend_from_slice(&bytes[start..]);
+            encoded
+        }
+    }
+}
+
+/// address of the contract created by `sender` with `CREATE` when its nonce is `nonce`
+pub fn create_address(sender: Address, nonce: u64) -> Address {
+    let nonce = rlp_u64(nonce);
+
+    // rlp list of the sender and the nonce
+    let mut encoded = vec![0xc0 + 21 + nonce.len() as u8, 0x80 + 20];
+    encoded.extend_from_slice(sender.as_bytes());
+    encoded.extend_from_slice(&nonce);
+
+    let mut hash = [0; 32];
+    sha3(&encoded, &mut hash);
+    Address::from_slice(&hash[12..])
+}
+
+/// address of the contract created by `sender` with `CREATE2`
+pub fn create2_address(sender: Address, salt: U256, init_code: &[u8]) -> Address {
+    let mut code_hash = [0; 32];
+    sha3(init_code, &mut code_hash);
+
+    let mut salt_bytes = [0; 32];
+    salt.to_big_endian(&mut salt_bytes);
+
+    let mut encoded = vec![0xff];
+    encoded.extend_from_slice(sender.as_bytes());
+    encoded.extend_from_slice(&salt_bytes);
+    encoded.extend_from_slice(&code_hash);
+
+    let mut hash = [0; 32];
+    sha3(&encoded, &mut hash);
+    Address::from_slice(&hash[12..])
+}
+
+pub fn exec(
+    opcode: u8,
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+    context: &CallContext,
+    nonce: u64,
+    spec: SpecId,
+) -> Result<CallRequest, HaltReason> {
+    if context.is_static {
+        return Err(HaltReason::StateChangeDuringStaticCall);
+    }
+
+    let value = stack.pop()?;
+    let offset = stack.pop()?;
+    let size = stack.pop()?;
+    let salt = match opcode {
+        CREATE2 => stack.pop()?,
+        _ => U256::zero(),
+    };
+
+    // init code is limited and metered since Shanghai (EIP-3860)
+    let limit_initcode = spec.is_enabled_in(SpecId::Shanghai);
+    if limit_initcode && size > MAX_INITCODE_SIZE.into() {
+        return Err(HaltReason::OutOfGas);
+    }
+
+    let init_code = mload_n(memory, gas, offset, size)?.to_vec();
+    let words = gas::words(init_code.len() as u64);
+    if limit_initcode {
+        gas.record_cost(gas::INITCODE_WORD * words)?;
+    }
+
+    let address = match opcode {
+        CREATE => create_address(context.address, nonce),
+        CREATE2 => {
+            // the init code has to be hashed to get the address
+            gas.record_cost(gas::SHA3_WORD * words)?;
+            create2_address(context.address, salt, &init_code)
+        }
+        _ => unreachable!("Not a create opcode"),
+    };
+
+    // before EIP-150 all of it was passed on
+    let gas_limit = if spec.is_enabled_in(SpecId::TangerineWhistle) {
+        gas.all_but_one_64th()
+    } else {
+        gas.remaining()
+    };
+    gas.record_cost(gas_limit)?;
+
+    Ok(CallRequest {
+        code_address: address,
+        init_code: Some(init_code),
+        context: CallContext {
+            caller: context.address,
+            address,
+            value,
+            calldata: Vec::new(),
+            is_static: false,
+        },
+        transfer: value,
+        gas_limit,
+        ret_offset: U256::zero(),
+        ret_size: U256::zero(),
+    })
+}
+
+/// check the runtime code returned by init code and charge for storing it
+pub fn deposit_code(gas: &mut Gas, code: &[u8], spec: SpecId) -> Result<(), HaltReason> {
+    if spec.is_enabled_in(SpecId::SpuriousDragon) && code.len() > MAX_CODE_SIZE {
+        return Err(HaltReason::CodeSizeLimit);
+    }
+
+    // code starting with 0xef is reserved since London (EIP-3541)
+    if spec.is_enabled_in(SpecId::London) && code.first() == Some(&0xef) {
+        return Err(HaltReason::InvalidCodePrefix);
+    }
+
+    gas.record_cost(gas::CODE_DEPOSIT * code.len() as u64)
+}
+
+/// hand the outcome of a finished creation back to the creator
+pub fn finish(
+    stack: &mut Stack,
+    gas: &mut Gas,
+    return_data: &mut Vec<u8>,
+    address: Address,
+    outcome: FrameOutcome,
+) {
+    gas.return_gas(outcome.gas_left);
+
+    // the creation's arguments were popped, so there's always room for the result
+    let result = if outcome.reason.is_success() {
+        return_data.clear();
+        address_to_word(address)
+    } else {
+        // only a revert's output is kept, a successful one is the deployed code
+        *return_data = if outcome.reason == HaltReason::Revert {
+            outcome.output
+        } else {
+            Vec::new()
+        };
+        U256::zero()
+    };
+    stack.push(result).expect("no room for the creation result");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create2_address, create_address};
+    use crate::tests::test_env;
+    use crate::{execute, Address, Database, Env, HaltReason, InMemoryDb};
+    use primitive_types::U256;
+
+    // init code: PUSH10 <runtime>\nPUSH1 0\nMSTORE\nPUSH1 10\nPUSH1 22\nRETURN
+    // runtime: PUSH1 0x2a\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nRETURN
+    const RUNTIME: &str = "602a60005260206000f3";
+
+    fn address(hex: &str) -> Address {
+        Address::from_slice(&hex::decode(hex).unwrap())
+    }
+
+    fn word(address: Address) -> U256 {
+        U256::from_big_endian(address.as_bytes())
+    }
+
+    #[test]
+    fn create_addresses() {
+        let sender = address("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        let expected = [
+            "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d",
+            "343c43a37d37dff08ae8c4a11544c718abb4fcf8",
+            "f778b86fa74e846c4f0a1fbd1335fe81c00a0c91",
+            "fffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c",
+        ];
+        for (nonce, expected) in expected.iter().enumerate() {
+            assert_eq!(create_address(sender, nonce as u64), address(expected));
+        }
+    }
+
+    #[test]
+    fn create2_addresses() {
+        // examples from EIP-1014
+        let cases = [
+            (
+                "0000000000000000000000000000000000000000",
+                "00",
+                "00",
+                "4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38",
+            ),
+            (
+                "deadbeef00000000000000000000000000000000",
+                "00",
+                "00",
+                "b928f69bb1d91cd65274e3c79d8986362984fda3",
+            ),
+            (
+                "deadbeef00000000000000000000000000000000",
+                "000000000000000000000000feed000000000000000000000000000000000000",
+                "00",
+                "d04116cdd17bebe565eb2422f2497e06cc1c9833",
+            ),
+            (
+                "0000000000000000000000000000000000000000",
+                "00",
+                "deadbeef",
+                "70f2b2914a2a4b783faefb75f459a580616fcb5e",
+            ),
+            (
+                "00000000000000000000000000000000deadbeef",
+                "cafebabe",
+                "deadbeef",
+                "60f3f640a8508fc6a86d45df051962668e1e8ac7",
+            ),
+            (
+                "00000000000000000000000000000000deadbeef",
+                "cafebabe",
+                "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef",
+                "1d8bfdc5d46dc4f61d6b6115972536ebe6a8854c",
+            ),
+            (
+                "0000000000000000000000000000000000000000",
+                "00",
+                "",
+                "e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0",
+            ),
+        ];
+        for (sender, salt, init_code, expected) in cases {
+            let salt = U256::from_str_radix(salt, 16).unwrap();
+            let init_code = hex::decode(init_code).unwrap();
+            assert_eq!(
+                create2_address(address(sender), salt, &init_code),
+                address(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn deploy_and_call() {
+        let mut db = InMemoryDb::new();
+        let env = test_env();
+        let created = create_address(env.address, 0);
+
+        // CODECOPY the init code\nCREATE\nCALL the new contract\nPUSH1 0\nMLOAD
+        let bin = "6013601f600039601360006000f060206000600060006000855af16000510069602a60005260206000f3600052600a6016f3";
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut db);
+        assert!(result.success);
+        assert_eq!(result.stack, vec![word(created), 1.into(), 0x2a.into()]);
+
+        assert_eq!(db.code(created), hex::decode(RUNTIME).unwrap());
+        assert_eq!(db.nonce(env.address), 1);
+        assert_eq!(db.nonce(created), 1);
+    }
+
+    #[test]
+    fn nonce() {
+        let mut db = InMemoryDb::new();
+        let env = test_env();
+
+        // CODECOPY the init code\nCREATE\nCREATE
+        let bin =
+            "60136016600039601360006000f0601360006000f00069602a60005260206000f3600052600a6016f3";
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut db);
+        assert_eq!(
+            result.stack,
+            vec![
+                word(create_address(env.address, 0)),
+                word(create_address(env.address, 1)),
+            ]
+        );
+        assert_eq!(db.nonce(env.address), 2);
+    }
+
+    #[test]
+    fn nonce_overflow() {
+        let mut db = InMemoryDb::new();
+        let env = test_env();
+        db.set_nonce(env.address, u64::MAX);
+
+        // a nonce that can't go up fails the creation (EIP-2681)
+        // PUSH1 0\nPUSH1 0\nPUSH1 0\nCREATE
+        let result = execute(hex::decode("600060006000f0").unwrap(), &env, &mut db);
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into()]);
+        assert_eq!(db.nonce(env.address), u64::MAX);
+
+        // and hands back the gas it was given
+        assert_eq!(result.gas_used, 3 * 3 + 32000);
+    }
+
+    #[test]
+    fn create2() {
+        let mut db = InMemoryDb::new();
+        let env = test_env();
+        let init_code = hex::decode("69602a60005260206000f3600052600a6016f3").unwrap();
+        let created = create2_address(env.address, 0x42.into(), &init_code);
+
+        // CODECOPY the init code\nCREATE2 with a salt of 0x42
+        let bin = "601360116000396042601360006000f50069602a60005260206000f3600052600a6016f3";
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut db);
+        assert_eq!(result.stack, vec![word(created)]);
+        assert_eq!(db.code(created), hex::decode(RUNTIME).unwrap());
+
+        // the same salt again collides with the first contract
+        let bin = "6013601a6000396042601360006000f56042601360006000f50069602a60005260206000f3600052600a6016f3";
+        let mut db = InMemoryDb::new();
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut db);
+        assert!(result.success);
+        assert_eq!(result.stack, vec![word(created), 0.into()]);
+    }
+
+    #[test]
+    fn code_size_limit() {
+        // depositing the largest allowed code costs almost 5 million gas
+        let env = Env {
+            gas_limit: 10_000_000,
+            ..test_env()
+        };
+
+        // init code: PUSH2 0x6000\nPUSH1 0\nRETURN
+        let bin = "6006600f600039600660006000f0006160006000f3";
+        let mut db = InMemoryDb::new();
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut db);
+        let created = create_address(env.address, 0);
+        assert_eq!(result.stack, vec![word(created)]);
+        assert_eq!(db.code(created).len(), 0x6000);
+
+        // init code: PUSH2 0x6001\nPUSH1 0\nRETURN
+        let bin = "6006600f600039600660006000f0006160016000f3";
+        let mut db = InMemoryDb::new();
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut db);
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into()]);
+        assert!(db.code(created).is_empty());
+    }
+
+    #[test]
+    fn ef_prefix() {
+        let mut db = InMemoryDb::new();
+
+        // init code: PUSH1 0xef\nPUSH1 0\nMSTORE8\nPUSH1 1\nPUSH1 0\nRETURN
+        let bin = "600a600f600039600a60006000f00060ef60005360016000f3";
+        let result = execute(hex::decode(bin).unwrap(), &test_env(), &mut db);
+        assert_eq!(result.stack, vec![0.into()]);
+    }
+
+    #[test]
+    fn initcode_limit() {
+        let mut db = InMemoryDb::new();
+        let env = test_env();
+
+        // PUSH2 0xc000\nPUSH1 0\nPUSH1 0\nCREATE
+        let result = execute(hex::decode("61c00060006000f0").unwrap(), &env, &mut db);
+        assert_eq!(result.stack, vec![word(create_address(env.address, 0))]);
+
+        // PUSH2 0xc001\nPUSH1 0\nPUSH1 0\nCREATE
+        let result = execute(hex::decode("61c00160006000f0").unwrap(), &env, &mut db);
+        assert_eq!(result.reason, HaltReason::OutOfGas);
+    }
+
+    #[test]
+    fn revert() {
+        let mut db = InMemoryDb::new();
+        let env = test_env();
+
+        // init code: PUSH1 0x2a\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nREVERT
+        // CODECOPY the init code\nCREATE\nRETURNDATASIZE
+        let bin = "600a6010600039600a60006000f03d00602a60005260206000fd";
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut db);
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into(), 32.into()]);
+
+        // the creator's nonce still goes up
+        assert_eq!(db.nonce(env.address), 1);
+    }
+
+    #[test]
+    fn staticcall() {
+        let mut db = InMemoryDb::new();
+
+        // callee: PUSH1 0\nPUSH1 0\nPUSH1 0\nCREATE
+        db.set_code(
+            Address::from_low_u64_be(0xbeef),
+            hex::decode("600060006000f0").unwrap(),
+        );
+
+        // STATICCALL 0xbeef
+        let bin = "600060006000600061beef5afa";
+        let result = execute(hex::decode(bin).unwrap(), &test_env(), &mut db);
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into()]);
+    }
+}
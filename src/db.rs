@@ -0,0 +1,108 @@
+use crate::state::{code_hash, Account};
+use crate::Address;
+use primitive_types::{H256, U256};
+use std::collections::HashMap;
+
+/// world state backend shared between executions
+pub trait Database {
+    /// value of `key` in the storage of `address`, zero if never written
+    fn sload(&self, address: Address, key: U256) -> U256;
+    fn sstore(&mut self, address: Address, key: U256, value: U256);
+    /// code deployed at `address`, empty if there is none
+    fn code(&self, address: Address) -> Vec<u8>;
+    fn set_code(&mut self, address: Address, code: Vec<u8>);
+    /// keccak256 of the code deployed at `address`
+    fn code_hash(&self, address: Address) -> H256;
+    fn nonce(&self, address: Address) -> u64;
+    fn set_nonce(&mut self, address: Address, nonce: u64);
+    fn balance(&self, address: Address) -> U256;
+    fn set_balance(&mut self, address: Address, balance: U256);
+    /// remove the account at `address` along with its storage
+    fn delete_account(&mut self, address: Address);
+}
+
+/// database backed by a `HashMap` of accounts
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryDb {
+    pub accounts: HashMap<Address, Account>,
+}
+
+impl InMemoryDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add `account` at `address`, replacing anything already there
+    pub fn insert_account(&mut self, address: Address, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    pub fn account(&self, address: Address) -> Option<&Account> {
+        self.accounts.get(&address)
+    }
+
+    // the account at `address`, creating an empty one if it doesn't exist yet
+    fn account_mut(&mut self, address: Address) -> &mut Account {
+        self.accounts.entry(address).or_default()
+    }
+}
+
+impl Database for InMemoryDb {
+    fn sload(&self, address: Address, key: U256) -> U256 {
+        self.account(address)
+            .and_then(|account| account.storage.get(&key).copied())
+            .unwrap_or_default()
+    }
+
+    fn sstore(&mut self, address: Address, key: U256, value: U256) {
+        // zero is the default, so don't keep it around
+        if value.is_zero() {
+            if let Some(account) = self.accounts.get_mut(&address) {
+                account.storage.remove(&key);
+            }
+        } else {
+            self.account_mut(address).storage.insert(key, value);
+        }
+    }
+
+    fn code(&self, address: Address) -> Vec<u8> {
+        self.account(address)
+            .map(|account| account.code.clone())
+            .unwrap_or_default()
+    }
+
+    fn set_code(&mut self, address: Address, code: Vec<u8>) {
+        self.account_mut(address).set_code(code);
+    }
+
+    fn code_hash(&self, address: Address) -> H256 {
+        match self.account(address) {
+            Some(account) => account.code_hash,
+            None => code_hash(&[]),
+        }
+    }
+
+    fn nonce(&self, address: Address) -> u64 {
+        self.account(address)
+            .map(|account| account.nonce)
+            .unwrap_or_default()
+    }
+
+    fn set_nonce(&mut self, address: Address, nonce: u64) {
+        self.account_mut(address).nonce = nonce;
+    }
+
+    fn balance(&self, address: Address) -> U256 {
+        self.account(address)
+            .map(|account| account.balance)
+            .unwrap_or_default()
+    }
+
+    fn set_balance(&mut self, address: Address, balance: U256) {
+        self.account_mut(address).balance = balance;
+    }
+
+    fn delete_account(&mut self, address: Address) {
+        self.accounts.remove(&address);
+    }
+}
@@ -1,38 +1,18 @@
-use crate::utils::Stack;
-use primitive_types::U256;
+use crate::stack::Stack;
+use crate::HaltReason;
 
 const DUP1: u8 = 0x80;
 const DUP16: u8 = 0x8f;
 const SWAP1: u8 = 0x90;
 const SWAP16: u8 = 0x9f;
 
-pub fn exec(opcode: u8, stack: &mut Vec<U256>) {
-    println!("opcode = {:x?}", opcode);
-    if opcode >= DUP1 && opcode <= DUP16 {
-        let depth: usize = (opcode - DUP1 + 1).into();
-        let left = stack.peek(depth).unwrap();
-        stack.push(*left);
-    } else if opcode >= SWAP1 && opcode <= SWAP16 {
-        let top = stack.safe_pop();
-        println!("top = {}", top);
-
-        let depth: usize = (opcode - SWAP1 + 1).into();
-        println!("depth = {}", depth);
-        let deep: &mut U256 = stack.peek_mut(depth).unwrap();
-        println!("deep = {}", deep);
-
-        // copy the deep item to push later
-        let new_top = deep.clone();
-        println!("new_top = {}", new_top);
-
-        // change the item deep in the stack
-        *deep = top;
-
-        // push the new top of the stack
-        stack.push(new_top);
-        println!("");
+pub fn exec(opcode: u8, stack: &mut Stack) -> Result<(), HaltReason> {
+    if (DUP1..=DUP16).contains(&opcode) {
+        stack.dup((opcode - DUP1 + 1).into())
+    } else if (SWAP1..=SWAP16).contains(&opcode) {
+        stack.swap((opcode - SWAP1 + 1).into())
     } else {
-        panic!("Opcode is not a DUP or SWAP");
+        unreachable!("Opcode is not a DUP or SWAP");
     }
 }
 
@@ -0,0 +1,302 @@
+use crate::gas::{self, Gas};
+use crate::memory::expand;
+use crate::stack::Stack;
+use crate::utils::padded_slice;
+use crate::{Address, BlockEnv, HaltReason, Memory, Precompiles, SpecId, DEFAULT_GAS_LIMIT};
+use primitive_types::{H256, U256};
+
+// environment opcodes
+
+const ADDRESS: u8 = 0x30;
+const ORIGIN: u8 = 0x32;
+const CALLER: u8 = 0x33;
+const CALLVALUE: u8 = 0x34;
+const CALLDATALOAD: u8 = 0x35;
+const CALLDATASIZE: u8 = 0x36;
+const CALLDATACOPY: u8 = 0x37;
+const CODESIZE: u8 = 0x38;
+const CODECOPY: u8 = 0x39;
+const GASPRICE: u8 = 0x3a;
+const RETURNDATASIZE: u8 = 0x3d;
+const RETURNDATACOPY: u8 = 0x3e;
+
+/// the context a piece of code is executed in
+#[derive(Debug, Clone)]
+pub struct Env {
+    /// account that signed the transaction
+    pub origin: Address,
+    /// account that made this call
+    pub caller: Address,
+    /// account whose code is running
+    pub address: Address,
+    /// wei sent along with the call
+    pub value: U256,
+    pub calldata: Vec<u8>,
+    pub gas_price: U256,
+    pub gas_limit: u64,
+    /// versioned hashes of the blobs carried by the transaction
+    pub blob_hashes: Vec<H256>,
+    pub block: BlockEnv,
+    /// fork whose rules execution follows
+    pub spec: SpecId,
+    /// addresses and storage slots that start the transaction warm (EIP-2930)
+    pub access_list: Vec<(Address, Vec<U256>)>,
+    /// natively implemented contracts, on top of the standard ones for `spec`
+    pub precompiles: Precompiles,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self {
+            origin: Address::zero(),
+            caller: Address::zero(),
+            address: Address::zero(),
+            value: U256::zero(),
+            calldata: Vec::new(),
+            gas_price: U256::zero(),
+            gas_limit: DEFAULT_GAS_LIMIT,
+            blob_hashes: Vec::new(),
+            block: BlockEnv::default(),
+            spec: SpecId::default(),
+            access_list: Vec::new(),
+            precompiles: Precompiles::new(),
+        }
+    }
+}
+
+/// the parts of the environment that change with every call frame
+#[derive(Debug, Clone, Default)]
+pub struct CallContext {
+    /// account that made this call
+    pub caller: Address,
+    /// account whose storage is in use
+    pub address: Address,
+    pub value: U256,
+    pub calldata: Vec<u8>,
+    /// whether state changes are forbidden
+    pub is_static: bool,
+}
+
+impl CallContext {
+    // the context of the outermost call
+    pub fn new(env: &Env) -> Self {
+        Self {
+            caller: env.caller,
+            address: env.address,
+            value: env.value,
+            calldata: env.calldata.clone(),
+            is_static: false,
+        }
+    }
+}
+
+// left pad an address into a word
+pub fn address_to_word(address: Address) -> U256 {
+    U256::from_big_endian(H256::from(address).as_bytes())
+}
+
+// the low 20 bytes of a word as an address
+pub fn word_to_address(word: U256) -> Address {
+    let mut bytes = [0; 32];
+    word.to_big_endian(&mut bytes);
+    Address::from_slice(&bytes[12..])
+}
+
+// pop a destination, source offset and size, then copy that part of `data` into memory
+// anything past the end of `data` is copied as zeros
+fn copy_to_memory(
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+    data: &[u8],
+) -> Result<(), HaltReason> {
+    let dest_offset = stack.pop()?;
+    let offset = stack.pop()?;
+    let size = stack.pop()?;
+
+    copy_slice(memory, gas, data, dest_offset, offset, size)
+}
+
+pub fn copy_slice(
+    memory: &mut Memory,
+    gas: &mut Gas,
+    data: &[u8],
+    dest_offset: U256,
+    offset: U256,
+    size: U256,
+) -> Result<(), HaltReason> {
+    if size.is_zero() {
+        return Ok(());
+    }
+
+    // expanding first also makes sure `size` is small enough to work with
+    expand(memory, gas, dest_offset, size)?;
+    gas.record_cost(gas::copy_cost(size.as_u64()))?;
+
+    // offsets past the end of `data` just read zeros
+    let offset = offset.min(data.len().into()).as_usize();
+    memory.set_data(dest_offset.as_usize(), data, offset, size.as_usize());
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn exec(
+    opcode: u8,
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+    env: &Env,
+    context: &CallContext,
+    code: &[u8],
+    return_data: &[u8],
+) -> Result<(), HaltReason> {
+    match opcode {
+        ADDRESS => stack.push(address_to_word(context.address))?,
+        ORIGIN => stack.push(address_to_word(env.origin))?,
+        CALLER => stack.push(address_to_word(context.caller))?,
+        CALLVALUE => stack.push(context.value)?,
+        CALLDATALOAD => {
+            let offset = stack.pop()?;
+            let bytes = padded_slice(&context.calldata, offset, 32);
+            stack.push(U256::from_big_endian(&bytes))?;
+        }
+        CALLDATASIZE => stack.push(context.calldata.len().into())?,
+        CALLDATACOPY => copy_to_memory(stack, memory, gas, &context.calldata)?,
+        CODESIZE => stack.push(code.len().into())?,
+        CODECOPY => copy_to_memory(stack, memory, gas, code)?,
+        GASPRICE => stack.push(env.gas_price)?,
+        RETURNDATASIZE => stack.push(return_data.len().into())?,
+        RETURNDATACOPY => {
+            let dest_offset = stack.pop()?;
+            let offset = stack.pop()?;
+            let size = stack.pop()?;
+
+            // unlike the other copies, reading past the end is an error
+            match offset.checked_add(size) {
+                Some(end) if end <= return_data.len().into() => (),
+                _ => return Err(HaltReason::ReturnDataOutOfBounds),
+            }
+
+            copy_slice(memory, gas, return_data, dest_offset, offset, size)?;
+        }
+        _ => unreachable!("Not an environment opcode"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Env;
+    use crate::{execute, Address, HaltReason, InMemoryDb};
+    use primitive_types::U256;
+
+    fn run(bin: &str, env: &Env) -> Vec<U256> {
+        let mut storage = InMemoryDb::new();
+        let result = execute(hex::decode(bin).unwrap(), env, &mut storage);
+        assert!(result.success, "{}", bin);
+        result.stack
+    }
+
+    fn test_env() -> Env {
+        Env {
+            origin: Address::from_low_u64_be(0x1337),
+            caller: Address::from_low_u64_be(0xca11),
+            address: Address::from_low_u64_be(0xc0de),
+            value: 1000.into(),
+            calldata: hex::decode(
+                "112233445566778899aabbccddeeff00112233445566778899aabbccddeeff0011",
+            )
+            .unwrap(),
+            gas_price: 7.into(),
+            ..Env::default()
+        }
+    }
+
+    #[test]
+    fn context() {
+        let env = test_env();
+        // ADDRESS\nORIGIN\nCALLER\nCALLVALUE\nGASPRICE
+        assert_eq!(
+            run("303233343a", &env),
+            vec![
+                0xc0de.into(),
+                0x1337.into(),
+                0xca11.into(),
+                1000.into(),
+                7.into()
+            ]
+        );
+    }
+
+    #[test]
+    fn calldata() {
+        let env = test_env();
+
+        // CALLDATASIZE
+        assert_eq!(run("36", &env), vec![33.into()]);
+
+        // PUSH1 0\nCALLDATALOAD
+        assert_eq!(
+            run("600035", &env),
+            vec![U256::from_big_endian(&env.calldata[..32])]
+        );
+
+        // reads past the end are zero padded
+        // PUSH1 31\nCALLDATALOAD
+        assert_eq!(run("601f35", &env), vec![U256::from(0x11) << 240]);
+
+        // PUSH32 MAX\nCALLDATALOAD
+        assert_eq!(
+            run(
+                "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff35",
+                &env
+            ),
+            vec![0.into()]
+        );
+
+        // PUSH1 4\nPUSH1 30\nPUSH1 0\nCALLDATACOPY\nPUSH1 0\nMLOAD\nMSIZE
+        assert_eq!(
+            run("6004601e60003760005159", &env),
+            vec![U256::from(0xff001100u64) << 224, 32.into()]
+        );
+    }
+
+    #[test]
+    fn code() {
+        let env = Env::default();
+
+        // CODESIZE\nPUSH1 8\nPUSH1 0\nPUSH1 0\nCODECOPY\nPUSH1 0\nMLOAD
+        assert_eq!(
+            run("3860086000600039600051", &env),
+            vec![11.into(), U256::from(0x3860086000600039u64) << 192]
+        );
+    }
+
+    #[test]
+    fn copy_gas() {
+        let env = test_env();
+        let mut storage = InMemoryDb::new();
+
+        // PUSH1 33\nPUSH1 0\nPUSH1 0\nCALLDATACOPY
+        let result = execute(hex::decode("60216000600037").unwrap(), &env, &mut storage);
+        assert_eq!(result.gas_used, 3 * 3 + 3 + 3 * 2 + 3 * 2);
+
+        // copying nothing is free, wherever it points
+        // PUSH1 0\nPUSH32 MAX\nPUSH32 MAX\nCALLDATACOPY
+        let result = execute(
+            hex::decode(concat!(
+                "6000",
+                "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "37"
+            ))
+            .unwrap(),
+            &env,
+            &mut storage,
+        );
+        assert_eq!(result.reason, HaltReason::Stop);
+        assert_eq!(result.gas_used, 3 * 3 + 3);
+    }
+}
@@ -1,63 +1,47 @@
-use crate::utils::Stack;
-use crate::EvmResult;
+use crate::bytecode::AnalyzedBytecode;
+use crate::stack::Stack;
+use crate::HaltReason;
 use primitive_types::U256;
 
-/// control flow opcode implementations
+// control flow opcode implementations
 
 const JUMP: u8 = 0x56;
 const JUMPI: u8 = 0x57;
 const JUMPDEST: u8 = 0x5b;
 
-fn jump(
-    stack: &mut Vec<U256>,
-    pc: &mut usize,
-    code: &[u8],
-    jumps: &Vec<bool>,
-    dest: U256,
-) -> Option<EvmResult> {
-    println!("dest = {}", dest);
-
-    if dest >= code.len().into() {
-        panic!("Jump destination is out of bounds");
-    }
-    *pc = dest.as_usize();
-
-    if let Some(jump) = jumps.get(*pc) {
-        if !jump {
-            return Some(EvmResult {
-                stack: stack.clone(),
-                success: false,
-            });
-        }
+fn jump(pc: &mut usize, code: &AnalyzedBytecode, dest: U256) -> Result<(), HaltReason> {
+    let dest = usize::try_from(dest).map_err(|_| HaltReason::InvalidJump)?;
+    if !code.is_jumpdest(dest) {
+        return Err(HaltReason::InvalidJump);
     }
+    *pc = dest;
 
-    None
+    Ok(())
 }
 
 pub fn exec(
     opcode: u8,
-    stack: &mut Vec<U256>,
+    stack: &mut Stack,
     pc: &mut usize,
-    code: &[u8],
-    jumps: &Vec<bool>,
-) -> Option<EvmResult> {
+    code: &AnalyzedBytecode,
+) -> Result<(), HaltReason> {
     match opcode {
         JUMP => {
-            let dest = stack.safe_pop();
-            return jump(stack, pc, code, jumps, dest);
+            let dest = stack.pop()?;
+            return jump(pc, code, dest);
         }
         JUMPI => {
-            let dest = stack.safe_pop();
-            let cond = stack.safe_pop();
+            let dest = stack.pop()?;
+            let cond = stack.pop()?;
             if cond != U256::zero() {
-                return jump(stack, pc, code, jumps, dest);
+                return jump(pc, code, dest);
             }
         }
         JUMPDEST => (),
-        _ => panic!("Not a control flow opcode"),
+        _ => unreachable!("Not a control flow opcode"),
     }
 
-    return None;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -80,6 +64,12 @@ mod tests {
                 vec![],
                 false,
             ),
+            TestSetup::new(
+                "PUSH9 0x010000000000000000\nJUMP",
+                "6801000000000000000056",
+                vec![],
+                false,
+            ),
         ];
 
         for setup in setups {
@@ -0,0 +1,395 @@
+use crate::opcode::OPCODE_TABLE;
+use crate::spec::SpecId::{self, *};
+use crate::HaltReason;
+use primitive_types::U256;
+
+// gas cost tiers from the yellow paper
+pub const ZERO: u64 = 0;
+pub const JUMPDEST: u64 = 1;
+pub const BASE: u64 = 2;
+pub const VERYLOW: u64 = 3;
+pub const LOW: u64 = 5;
+pub const MID: u64 = 8;
+pub const HIGH: u64 = 10;
+
+// dynamic cost components
+pub const EXP_BYTE: u64 = 50;
+pub const EXP_BYTE_FRONTIER: u64 = 10;
+pub const SHA3_WORD: u64 = 6;
+pub const COPY_WORD: u64 = 3;
+pub const SSTORE_SET: u64 = 20000;
+pub const SSTORE_RESET: u64 = 5000;
+pub const CALL_VALUE: u64 = 9000;
+pub const CALL_STIPEND: u64 = 2300;
+pub const INITCODE_WORD: u64 = 2;
+pub const CODE_DEPOSIT: u64 = 200;
+pub const LOG_DATA: u64 = 8;
+pub const NEW_ACCOUNT: u64 = 25000;
+pub const SELFDESTRUCT_REFUND: i64 = 24000;
+pub const SSTORE_CLEARS: i64 = 15000;
+pub const SSTORE_CLEARS_LONDON: i64 = 4800;
+pub const REFUND_QUOTIENT: u64 = 5;
+pub const REFUND_QUOTIENT_FRONTIER: u64 = 2;
+pub const COLD_SLOAD: u64 = 2100;
+pub const COLD_ACCOUNT_ACCESS: u64 = 2600;
+pub const WARM_STORAGE_READ: u64 = 100;
+pub const MEMORY_WORD: u64 = 3;
+pub const QUAD_COEFF_DIV: u64 = 512;
+
+/// gas limit used by `evm` when none is given, one mainnet block's worth
+pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+
+pub struct Gas {
+    limit: u64,
+    used: u64,
+    // can dip below zero while a sub call undoes a refunded change
+    refunded: i64,
+}
+
+impl Gas {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: 0,
+            refunded: 0,
+        }
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.limit - self.used
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn refunded(&self) -> i64 {
+        self.refunded
+    }
+
+    // add to the refund paid out at the end of the transaction
+    pub fn record_refund(&mut self, refund: i64) {
+        self.refunded += refund;
+    }
+
+    /// the refund actually paid out, capped at a share of the gas used (EIP-3529)
+    pub fn final_refund(&self, spec: SpecId) -> u64 {
+        let quotient = if spec.is_enabled_in(London) {
+            REFUND_QUOTIENT
+        } else {
+            REFUND_QUOTIENT_FRONTIER
+        };
+        (self.refunded.max(0) as u64).min(self.used / quotient)
+    }
+
+    // charge `cost`, failing without charging anything if there isn't enough left
+    pub fn record_cost(&mut self, cost: u64) -> Result<(), HaltReason> {
+        if cost > self.remaining() {
+            return Err(HaltReason::OutOfGas);
+        }
+        self.used += cost;
+
+        Ok(())
+    }
+
+    // give back gas a sub call didn't use
+    pub fn return_gas(&mut self, gas: u64) {
+        self.used -= gas;
+    }
+
+    // exceptional halts burn everything that's left
+    pub fn consume_all(&mut self) {
+        self.used = self.limit;
+    }
+
+    // the most a sub call or creation can be given, keeping back one 64th (EIP-150)
+    pub fn all_but_one_64th(&self) -> u64 {
+        self.remaining() - self.remaining() / 64
+    }
+}
+
+// number of 32 byte words needed to hold `bytes`
+pub fn words(bytes: u64) -> u64 {
+    bytes.div_ceil(32)
+}
+
+// total cost of having `size` bytes of memory allocated
+pub fn memory_cost(size: u64) -> u64 {
+    let words = words(size);
+    MEMORY_WORD * words + words * words / QUAD_COEFF_DIV
+}
+
+// per word cost of copying `size` bytes
+pub fn copy_cost(size: u64) -> u64 {
+    COPY_WORD * words(size)
+}
+
+// dynamic part of `EXP`, paid per byte of the exponent
+pub fn exp_cost(exponent_bytes: u64, spec: SpecId) -> u64 {
+    if spec.is_enabled_in(SpuriousDragon) {
+        EXP_BYTE * exponent_bytes
+    } else {
+        EXP_BYTE_FRONTIER * exponent_bytes
+    }
+}
+
+// cost from the latest fork in `schedule` that `spec` includes, listed oldest first
+fn repriced(spec: SpecId, schedule: &[(SpecId, u64)]) -> u64 {
+    schedule
+        .iter()
+        .rev()
+        .find(|(fork, _)| spec.is_enabled_in(*fork))
+        .map_or(ZERO, |(_, cost)| *cost)
+}
+
+// cost of `SLOAD`, which net metered `SSTORE` also charges for writes that change nothing
+fn sload_cost(spec: SpecId) -> u64 {
+    repriced(
+        spec,
+        &[
+            (Frontier, 50),
+            (TangerineWhistle, 200),
+            (Istanbul, 800),
+            (Berlin, WARM_STORAGE_READ),
+        ],
+    )
+}
+
+// whether `SSTORE` is priced against the slot's original value (EIP-1283, EIP-2200),
+// Petersburg took it back out again
+fn net_metered(spec: SpecId) -> bool {
+    spec == Constantinople || spec.is_enabled_in(Istanbul)
+}
+
+// cost of changing a slot that still holds its original value, which isn't zero
+fn sstore_reset(spec: SpecId) -> u64 {
+    if spec.is_enabled_in(Berlin) {
+        SSTORE_RESET - COLD_SLOAD
+    } else {
+        SSTORE_RESET
+    }
+}
+
+// refund for clearing a slot
+fn sstore_clears(spec: SpecId) -> i64 {
+    if spec.is_enabled_in(London) {
+        SSTORE_CLEARS_LONDON
+    } else {
+        SSTORE_CLEARS
+    }
+}
+
+/// dynamic cost of `SSTORE` writing `new` over `current`, not counting cold access
+pub fn sstore_cost(original: U256, current: U256, new: U256, spec: SpecId) -> u64 {
+    if !net_metered(spec) {
+        return if current.is_zero() && !new.is_zero() {
+            SSTORE_SET
+        } else {
+            SSTORE_RESET
+        };
+    }
+
+    // only the first change to a slot in a transaction is charged in full
+    if current == new || original != current {
+        sload_cost(spec)
+    } else if original.is_zero() {
+        SSTORE_SET
+    } else {
+        sstore_reset(spec)
+    }
+}
+
+/// refund earned, or taken back if negative, by `SSTORE` writing `new` over `current`
+pub fn sstore_refund(original: U256, current: U256, new: U256, spec: SpecId) -> i64 {
+    let clears = sstore_clears(spec);
+    if !net_metered(spec) {
+        return if !current.is_zero() && new.is_zero() {
+            clears
+        } else {
+            0
+        };
+    }
+
+    if current == new {
+        return 0;
+    }
+    if original == current {
+        return if !original.is_zero() && new.is_zero() {
+            clears
+        } else {
+            0
+        };
+    }
+
+    // the slot was already changed once in this transaction
+    let mut refund = 0;
+    if !original.is_zero() {
+        if current.is_zero() {
+            refund -= clears;
+        }
+        if new.is_zero() {
+            refund += clears;
+        }
+    }
+
+    // putting the original value back gives back what the first change cost
+    if original == new {
+        let first = if original.is_zero() {
+            SSTORE_SET
+        } else {
+            sstore_reset(spec)
+        };
+        refund += (first - sload_cost(spec)) as i64;
+    }
+
+    refund
+}
+
+/// cost of sending to an account that may be created, which until EIP-161 applied whether or not
+/// value was sent and from then on only when it is; `SELFDESTRUCT` started paying it in EIP-150
+pub fn new_account_cost(
+    spec: SpecId,
+    sends_value: bool,
+    is_empty: bool,
+    charged_before_eip150: bool,
+) -> u64 {
+    let charged = if spec.is_enabled_in(SpuriousDragon) {
+        sends_value && is_empty
+    } else {
+        is_empty && (charged_before_eip150 || spec.is_enabled_in(TangerineWhistle))
+    };
+    if charged {
+        NEW_ACCOUNT
+    } else {
+        ZERO
+    }
+}
+
+// dynamic part of `SHA3`, paid per word hashed
+pub fn sha3_cost(size: u64) -> u64 {
+    SHA3_WORD * words(size)
+}
+
+/// the fixed cost charged before executing `opcode` under `spec`
+pub fn static_cost(opcode: u8, spec: SpecId) -> u64 {
+    match opcode {
+        // BALANCE
+        0x31 => repriced(
+            spec,
+            &[
+                (Frontier, 20),
+                (TangerineWhistle, 400),
+                (Istanbul, 700),
+                (Berlin, 100),
+            ],
+        ),
+        // EXTCODESIZE, EXTCODECOPY
+        0x3b | 0x3c => repriced(
+            spec,
+            &[(Frontier, 20), (TangerineWhistle, 700), (Berlin, 100)],
+        ),
+        // EXTCODEHASH
+        0x3f => repriced(
+            spec,
+            &[(Constantinople, 400), (Istanbul, 700), (Berlin, 100)],
+        ),
+        // SLOAD
+        0x54 => sload_cost(spec),
+        // CALL, CALLCODE, DELEGATECALL, STATICCALL
+        0xf1 | 0xf2 | 0xf4 | 0xfa => repriced(
+            spec,
+            &[(Frontier, 40), (TangerineWhistle, 700), (Berlin, 100)],
+        ),
+        // SELFDESTRUCT
+        0xff => repriced(spec, &[(TangerineWhistle, 5000)]),
+        // everything else costs the same as when it was introduced
+        _ => OPCODE_TABLE[opcode as usize].map_or(ZERO, |info| info.gas),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{evm, evm_with_gas, HaltReason};
+
+    // (asm, bytecode, gas used)
+    fn check_gas_used(cases: Vec<(&str, &str, u64)>) {
+        for (asm, bin, gas_used) in cases {
+            let result = evm(hex::decode(bin).unwrap());
+            assert!(result.success, "{}", asm);
+            assert_eq!(result.gas_used, gas_used, "{}", asm);
+        }
+    }
+
+    #[test]
+    fn static_costs() {
+        check_gas_used(vec![
+            ("STOP", "00", 0),
+            ("PUSH1 1\nPUSH1 2\nADD", "6001600201", 9),
+            ("PUSH1 1\nPUSH1 2\nMUL", "6001600202", 11),
+            ("PUSH1 1\nPUSH1 2\nPUSH1 3\nADDMOD", "60016002600308", 17),
+            ("PUSH0\nPOP\nPC", "5f5058", 6),
+            ("JUMPDEST", "5b", 1),
+            ("PUSH1 3\nJUMP\nJUMPDEST", "6003565b", 12),
+            ("PUSH1 1\nPUSH1 5\nJUMPI\nJUMPDEST", "60016005575b", 17),
+        ]);
+    }
+
+    #[test]
+    fn exp() {
+        check_gas_used(vec![
+            ("PUSH1 0\nPUSH1 2\nEXP", "600060020a", 16),
+            ("PUSH1 10\nPUSH1 2\nEXP", "600a60020a", 66),
+            ("PUSH2 0x0100\nPUSH1 2\nEXP", "61010060020a", 116),
+        ]);
+    }
+
+    #[test]
+    fn memory_expansion() {
+        check_gas_used(vec![
+            ("PUSH1 0\nMLOAD", "600051", 9),
+            ("PUSH1 1\nMLOAD", "600151", 12),
+            ("PUSH1 0\nMLOAD\nPUSH1 0\nMLOAD", "600051600051", 15),
+            ("PUSH1 0xff\nPUSH1 0\nMSTORE8", "60ff600053", 12),
+            // 0x400 words costs 3 * 0x400 + 0x400 * 0x400 / 512
+            ("PUSH2 0x7fe0\nMLOAD", "617fe051", 3 + 3 + 3 * 1024 + 2048),
+        ]);
+    }
+
+    #[test]
+    fn sha3() {
+        check_gas_used(vec![
+            ("PUSH1 0\nPUSH1 0\nSHA3", "6000600020", 36),
+            ("PUSH1 32\nPUSH1 0\nSHA3", "6020600020", 45),
+            ("PUSH1 33\nPUSH1 0\nSHA3", "6021600020", 54),
+        ]);
+    }
+
+    #[test]
+    fn gas_opcode() {
+        let result = evm_with_gas(hex::decode("5a").unwrap(), 100);
+        assert_eq!(result.stack, vec![98.into()]);
+        assert_eq!(result.gas_left, 98);
+    }
+
+    #[test]
+    fn out_of_gas() {
+        // JUMPDEST\nPUSH1 0\nJUMP loops forever
+        let result = evm_with_gas(hex::decode("5b600056").unwrap(), 1000);
+        assert_eq!(result.reason, HaltReason::OutOfGas);
+        assert!(!result.success);
+        assert_eq!(result.gas_used, 1000);
+        assert_eq!(result.gas_left, 0);
+
+        // not enough for the memory expansion
+        let result = evm_with_gas(hex::decode("600051").unwrap(), 8);
+        assert_eq!(result.reason, HaltReason::OutOfGas);
+        assert_eq!(result.pc, 2);
+    }
+
+    #[test]
+    fn exceptional_halt_consumes_all_gas() {
+        let result = evm_with_gas(hex::decode("6001fe").unwrap(), 100);
+        assert_eq!(result.reason, HaltReason::InvalidOpcode);
+        assert_eq!(result.gas_used, 100);
+    }
+}
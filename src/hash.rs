@@ -1,34 +1,43 @@
 use tiny_keccak::{Keccak, Hasher};
+use crate::gas::{self, Gas};
 use crate::memory::mload_n;
 use primitive_types::U256;
-use crate::Memory;
-use crate::utils::Stack;
+use crate::{HaltReason, Memory};
+use crate::stack::Stack;
 
 const SHA3: u8 = 0x20;
 
-fn sha3(input: &[u8], output: &mut [u8]) {
+pub fn sha3(input: &[u8], output: &mut [u8]) {
     let mut keccak = Keccak::v256();
     keccak.update(input);
     keccak.finalize(output);
 }
 
-pub fn exec(opcode: u8, stack: &mut Vec<U256>, memory: &mut Memory) {
+pub fn exec(
+    opcode: u8,
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+) -> Result<(), HaltReason> {
     match opcode {
         SHA3 => {
             // load offset and size from stack
-            let offset = stack.safe_pop();
-            let size = stack.safe_pop();
+            let offset = stack.pop()?;
+            let size = stack.pop()?;
 
             // load input from memory
-            let input = mload_n(memory, offset, size);
+            let input = mload_n(memory, gas, offset, size)?;
+            gas.record_cost(gas::sha3_cost(input.len() as u64))?;
             // init output
             let output = &mut [0; 32];
 
-            sha3(&input, output);
-            stack.push(U256::from_big_endian(output));
+            sha3(input, output);
+            stack.push(U256::from_big_endian(output))?;
         }
-        _ => panic!("Not a hash opcode"),
+        _ => unreachable!("Not a hash opcode"),
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
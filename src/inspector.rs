@@ -0,0 +1,168 @@
+use crate::env::CallContext;
+use crate::{Address, HaltReason, Log, Memory, Stack};
+
+/// the frame around a single instruction, as seen by an `Inspector`
+pub struct Step<'a> {
+    /// number of frames below this one, 0 for the outermost
+    pub depth: usize,
+    pub pc: usize,
+    pub opcode: u8,
+    /// account whose code is running
+    pub address: Address,
+    pub gas_left: u64,
+    pub stack: &'a Stack,
+    pub memory: &'a Memory,
+}
+
+/// hooks for observing execution, all of which do nothing unless overridden
+pub trait Inspector {
+    /// before each instruction runs, including the implicit `STOP` past the end of the code
+    fn step(&mut self, _step: &Step) {}
+
+    /// after each instruction, with the reason if it halted the frame
+    fn step_end(&mut self, _step: &Step, _reason: Option<HaltReason>) {}
+
+    /// a call opcode is starting a sub call running the code at `code_address`
+    fn call(&mut self, _context: &CallContext, _code_address: Address, _gas_limit: u64) {}
+
+    /// a sub call or creation finished, including ones that failed before running anything
+    fn call_end(&mut self, _reason: HaltReason, _output: &[u8], _gas_left: u64) {}
+
+    /// a `LOG` opcode emitted `log`
+    fn log(&mut self, _log: &Log) {}
+
+    /// a create opcode is starting to run `init_code` for the account at `context.address`
+    fn create(&mut self, _context: &CallContext, _init_code: &[u8], _gas_limit: u64) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Inspector, Step};
+    use crate::env::CallContext;
+    use crate::{
+        execute, execute_with_inspector, Address, Database, Env, HaltReason, InMemoryDb, Log,
+    };
+
+    #[derive(Default)]
+    struct Recorder {
+        // (depth, pc, opcode, stack length before, stack length after)
+        steps: Vec<(usize, usize, u8, usize, usize)>,
+        halts: Vec<HaltReason>,
+        events: Vec<String>,
+    }
+
+    impl Inspector for Recorder {
+        fn step(&mut self, step: &Step) {
+            self.steps
+                .push((step.depth, step.pc, step.opcode, step.stack.len(), 0));
+        }
+
+        fn step_end(&mut self, step: &Step, reason: Option<HaltReason>) {
+            let last = self.steps.last_mut().unwrap();
+            assert_eq!((last.1, last.2), (step.pc, step.opcode));
+            last.4 = step.stack.len();
+            self.halts.extend(reason);
+        }
+
+        fn call(&mut self, context: &CallContext, code_address: Address, gas_limit: u64) {
+            assert!(gas_limit > 0);
+            self.events.push(format!(
+                "call {:#x} from {:#x}",
+                code_address.to_low_u64_be(),
+                context.caller.to_low_u64_be()
+            ));
+        }
+
+        fn call_end(&mut self, reason: HaltReason, output: &[u8], _gas_left: u64) {
+            self.events
+                .push(format!("end {:?} 0x{}", reason, hex::encode(output)));
+        }
+
+        fn log(&mut self, log: &Log) {
+            self.events.push(format!("log {}", hex::encode(&log.data)));
+        }
+
+        fn create(&mut self, _context: &CallContext, init_code: &[u8], _gas_limit: u64) {
+            self.events
+                .push(format!("create {}", hex::encode(init_code)));
+        }
+    }
+
+    fn inspect(bin: &str, db: &mut InMemoryDb) -> Recorder {
+        let mut recorder = Recorder::default();
+        let code = hex::decode(bin).unwrap();
+        let env = Env {
+            address: Address::from_low_u64_be(0xc0de),
+            ..Env::default()
+        };
+        let inspected = execute_with_inspector(&code, &env, db, &mut recorder);
+
+        // watching doesn't change the outcome
+        let result = execute(&code, &env, &mut InMemoryDb::new());
+        assert_eq!(inspected.stack, result.stack);
+        recorder
+    }
+
+    #[test]
+    fn steps() {
+        // PUSH1 1\nPUSH1 2\nADD\nPOP\nPOP
+        let recorder = inspect("600160020150", &mut InMemoryDb::new());
+        assert_eq!(
+            recorder.steps,
+            vec![
+                (0, 0, 0x60, 0, 1),
+                (0, 2, 0x60, 1, 2),
+                (0, 4, 0x01, 2, 1),
+                (0, 5, 0x50, 1, 0),
+                // running off the end
+                (0, 6, 0x00, 0, 0),
+            ]
+        );
+        assert_eq!(recorder.halts, vec![HaltReason::Stop]);
+
+        // the instruction that fails is still reported
+        // PUSH1 1\nPOP\nPOP
+        let recorder = inspect("60015050", &mut InMemoryDb::new());
+        assert_eq!(recorder.steps.len(), 3);
+        assert_eq!(recorder.halts, vec![HaltReason::StackUnderflow]);
+    }
+
+    #[test]
+    fn calls() {
+        // 0xbeef runs PUSH1 0x2a\nPUSH1 0\nMSTORE8\nPUSH1 1\nPUSH1 0\nLOG0\nPUSH1 1\nPUSH1 0\nRETURN
+        let mut db = InMemoryDb::new();
+        db.set_code(
+            Address::from_low_u64_be(0xbeef),
+            hex::decode("602a60005360016000a060016000f3").unwrap(),
+        );
+
+        // PUSH1 0\nPUSH1 0\nPUSH1 0\nPUSH1 0\nPUSH1 0\nPUSH2 0xbeef\nGAS\nCALL
+        let recorder = inspect("6000600060006000600061beef5af1", &mut db);
+        assert_eq!(
+            recorder.events,
+            vec!["call 0xbeef from 0xc0de", "log 2a", "end Return 0x2a"]
+        );
+
+        // the callee's instructions run one frame deeper
+        let depths: Vec<usize> = recorder.steps.iter().map(|step| step.0).collect();
+        assert_eq!(depths.iter().filter(|&&depth| depth == 1).count(), 9);
+        assert_eq!(recorder.halts, vec![HaltReason::Return, HaltReason::Stop]);
+    }
+
+    #[test]
+    fn creates() {
+        // init code: PUSH1 0\nPUSH1 0\nRETURN
+        // PUSH5 <init code>\nPUSH1 0\nMSTORE\nPUSH1 5\nPUSH1 27\nPUSH1 0\nCREATE
+        let mut db = InMemoryDb::new();
+        let recorder = inspect("6460006000f36000526005601b6000f0", &mut db);
+        assert_eq!(recorder.events, vec!["create 60006000f3", "end Return 0x"]);
+
+        // calls which fail up front still end
+        // PUSH1 0\nPUSH1 0\nPUSH1 0\nPUSH1 0\nPUSH1 1\nPUSH2 0xbeef\nGAS\nCALL
+        let recorder = inspect("6000600060006000600161beef5af1", &mut db);
+        assert_eq!(
+            recorder.events,
+            vec!["call 0xbeef from 0xc0de", "end InsufficientBalance 0x"]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{HaltReason, Host, Program, PUSH1, PUSH32};
+use primitive_types::U256;
+
+/// executes `opcode` in the current frame, its static cost already paid
+pub type Instruction = fn(&mut Program, &mut Host, u8) -> Result<(), HaltReason>;
+
+/// every byte mapped to the handler that executes it
+pub static INSTRUCTIONS: [Instruction; 256] = {
+    let mut table = [invalid as Instruction; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = lookup(i as u8);
+        i += 1;
+    }
+    table
+};
+
+const fn lookup(opcode: u8) -> Instruction {
+    match opcode {
+        0x00 => stop,
+        0x01..=0x0b => arithmetic,
+        0x10..=0x1d => cmp,
+        0x20 => hash,
+        0x30 | 0x32..=0x3a | 0x3d | 0x3e => env,
+        0x31 | 0x3b | 0x3c | 0x3f | 0x47 => state,
+        0x40..=0x46 | 0x48..=0x4a => block,
+        0x50 => pop,
+        0x51..=0x53 | 0x59 | 0x5e => memory,
+        0x54 | 0x55 | 0x5c | 0x5d => storage,
+        0x56 | 0x57 | 0x5b => flow,
+        0x58 => pc,
+        0x5a => gas,
+        0x5f => push0,
+        PUSH1..=PUSH32 => push,
+        0x80..=0x9f => dup_swap,
+        0xa0..=0xa4 => log,
+        0xf0 | 0xf5 => create,
+        0xf1 | 0xf2 | 0xf4 | 0xfa => call,
+        0xf3 | 0xfd => system,
+        0xff => selfdestruct,
+        // INVALID, and anything defined for the fork but not supported yet
+        _ => invalid,
+    }
+}
+
+// basic opcodes
+
+fn stop(_program: &mut Program, _host: &mut Host, _opcode: u8) -> Result<(), HaltReason> {
+    Err(HaltReason::Stop)
+}
+
+fn invalid(_program: &mut Program, _host: &mut Host, _opcode: u8) -> Result<(), HaltReason> {
+    Err(HaltReason::InvalidOpcode)
+}
+
+fn pop(program: &mut Program, _host: &mut Host, _opcode: u8) -> Result<(), HaltReason> {
+    program.state.stack.pop()?;
+    Ok(())
+}
+
+fn pc(program: &mut Program, _host: &mut Host, _opcode: u8) -> Result<(), HaltReason> {
+    program.state.stack.push(U256::from(program.pc - 1))?;
+    Ok(())
+}
+
+fn gas(program: &mut Program, _host: &mut Host, _opcode: u8) -> Result<(), HaltReason> {
+    program.state.stack.push(program.gas.remaining().into())?;
+    Ok(())
+}
+
+fn push0(program: &mut Program, _host: &mut Host, _opcode: u8) -> Result<(), HaltReason> {
+    program.state.stack.push(U256::zero())?;
+    Ok(())
+}
+
+// push the bytes following the opcode onto the stack
+fn push(program: &mut Program, _host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    let size = (opcode - PUSH1 + 1) as usize;
+
+    // bytes past the end of the code read from the STOPs padding it
+    let start = program.pc;
+    let bytes = &program.code.padded_code()[start..start + size];
+
+    program.state.stack.push(U256::from_big_endian(bytes))?;
+    program.pc += size;
+    Ok(())
+}
+
+// everything else is handed to the module that implements it
+
+fn arithmetic(program: &mut Program, host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::arithmetic::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.gas,
+        host.env.spec,
+    )
+}
+
+fn cmp(program: &mut Program, _host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::cmp::exec(opcode, &mut program.state.stack)
+}
+
+fn dup_swap(program: &mut Program, _host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::dup_swap::exec(opcode, &mut program.state.stack)
+}
+
+fn flow(program: &mut Program, _host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::flow::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.pc,
+        &program.code,
+    )
+}
+
+fn memory(program: &mut Program, _host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::memory::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.state.memory,
+        &mut program.gas,
+    )
+}
+
+fn hash(program: &mut Program, _host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::hash::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.state.memory,
+        &mut program.gas,
+    )
+}
+
+fn env(program: &mut Program, host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::env::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.state.memory,
+        &mut program.gas,
+        host.env,
+        &program.context,
+        program.code.code(),
+        &program.return_data,
+    )
+}
+
+fn state(program: &mut Program, host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::state::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.state.memory,
+        &mut program.gas,
+        &mut host.journal,
+        &program.context,
+        host.env.spec,
+    )
+}
+
+fn block(program: &mut Program, host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::block::exec(opcode, &mut program.state.stack, host.env)
+}
+
+fn storage(program: &mut Program, host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::storage::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut host.journal,
+        &mut program.gas,
+        &program.context,
+        host.env.spec,
+    )
+}
+
+fn call(program: &mut Program, host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    let request = crate::call::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.state.memory,
+        &mut program.gas,
+        &program.context,
+        &mut host.journal,
+        host.env.spec,
+    )?;
+    program.call = Some(request);
+    Ok(())
+}
+
+fn log(program: &mut Program, host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::log::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.state.memory,
+        &mut program.gas,
+        &program.context,
+        &mut host.journal,
+    )?;
+
+    if let (Some(inspector), Some(log)) =
+        (host.inspector.as_deref_mut(), host.journal.logs().last())
+    {
+        inspector.log(log);
+    }
+    Ok(())
+}
+
+fn create(program: &mut Program, host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    let nonce = host.journal.nonce(program.context.address);
+    let request = crate::create::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.state.memory,
+        &mut program.gas,
+        &program.context,
+        nonce,
+        host.env.spec,
+    )?;
+    program.call = Some(request);
+    Ok(())
+}
+
+fn system(program: &mut Program, _host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    crate::system::exec(
+        opcode,
+        &mut program.state.stack,
+        &mut program.state.memory,
+        &mut program.gas,
+        &mut program.output,
+    )
+}
+
+// contracts deleting themselves
+fn selfdestruct(program: &mut Program, host: &mut Host, _opcode: u8) -> Result<(), HaltReason> {
+    crate::system::selfdestruct(
+        &mut program.state.stack,
+        &mut program.gas,
+        &mut host.journal,
+        &program.context,
+        host.env.spec,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{invalid, Instruction, INSTRUCTIONS};
+    use crate::opcode_name;
+    use std::ptr::fn_addr_eq;
+
+    #[test]
+    fn every_opcode_has_a_handler() {
+        for opcode in 0..=255u8 {
+            let handled = !fn_addr_eq(INSTRUCTIONS[opcode as usize], invalid as Instruction);
+            let assigned = opcode_name(opcode).is_some_and(|name| name != "INVALID");
+            assert_eq!(handled, assigned, "{:#x}", opcode);
+        }
+    }
+}
@@ -0,0 +1,455 @@
+use crate::log::Log;
+use crate::state::code_hash;
+use crate::{Address, Database};
+use primitive_types::{H256, U256};
+use std::collections::{HashMap, HashSet};
+
+/// a single change to the state, holding what's needed to undo it
+#[derive(Debug)]
+enum JournalEntry {
+    /// `previous` is `None` when the slot was never written in this transaction
+    StorageChanged {
+        address: Address,
+        key: U256,
+        previous: Option<U256>,
+    },
+    TransientChanged {
+        address: Address,
+        key: U256,
+        previous: Option<U256>,
+    },
+    BalanceChanged {
+        address: Address,
+        previous: Option<U256>,
+    },
+    NonceChanged {
+        address: Address,
+        previous: Option<u64>,
+    },
+    CodeChanged {
+        address: Address,
+        previous: Option<Vec<u8>>,
+    },
+    AccountCreated {
+        address: Address,
+    },
+    AccountDestroyed {
+        address: Address,
+    },
+    AddressWarmed {
+        address: Address,
+    },
+    SlotWarmed {
+        address: Address,
+        key: U256,
+    },
+    LogEmitted,
+}
+
+/// position in the journal that changes can be rolled back to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    entries: usize,
+}
+
+/// state changes made by a running transaction on top of a `Database`
+///
+/// nothing reaches the backend until `Journal::apply`, and every change is recorded so that
+/// a failed call frame can undo its own changes with `Journal::revert_to`
+pub struct Journal<'a> {
+    db: &'a mut dyn Database,
+    storage: HashMap<(Address, U256), U256>,
+    // transient storage (EIP-1153), thrown away at the end of the transaction
+    transient: HashMap<(Address, U256), U256>,
+    balances: HashMap<Address, U256>,
+    nonces: HashMap<Address, u64>,
+    code: HashMap<Address, Vec<u8>>,
+    created: HashSet<Address>,
+    // accounts deleted once the transaction is over
+    destroyed: HashSet<Address>,
+    // addresses and slots already touched in this transaction (EIP-2929)
+    accessed_addresses: HashSet<Address>,
+    accessed_slots: HashSet<(Address, U256)>,
+    logs: Vec<Log>,
+    entries: Vec<JournalEntry>,
+}
+
+impl<'a> Journal<'a> {
+    pub fn new(db: &'a mut dyn Database) -> Self {
+        Self {
+            db,
+            storage: HashMap::new(),
+            transient: HashMap::new(),
+            balances: HashMap::new(),
+            nonces: HashMap::new(),
+            code: HashMap::new(),
+            created: HashSet::new(),
+            destroyed: HashSet::new(),
+            accessed_addresses: HashSet::new(),
+            accessed_slots: HashSet::new(),
+            logs: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// mark the current state so that later changes can be undone
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            entries: self.entries.len(),
+        }
+    }
+
+    /// keep the changes made since `checkpoint`
+    ///
+    /// they can still be undone by reverting to an earlier checkpoint
+    pub fn commit(&mut self, checkpoint: Checkpoint) {
+        debug_assert!(checkpoint.entries <= self.entries.len());
+    }
+
+    /// undo every change made since `checkpoint`
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) {
+        while self.entries.len() > checkpoint.entries {
+            match self.entries.pop().unwrap() {
+                JournalEntry::StorageChanged {
+                    address,
+                    key,
+                    previous,
+                } => restore(&mut self.storage, (address, key), previous),
+                JournalEntry::TransientChanged {
+                    address,
+                    key,
+                    previous,
+                } => restore(&mut self.transient, (address, key), previous),
+                JournalEntry::BalanceChanged { address, previous } => {
+                    restore(&mut self.balances, address, previous)
+                }
+                JournalEntry::NonceChanged { address, previous } => {
+                    restore(&mut self.nonces, address, previous)
+                }
+                JournalEntry::CodeChanged { address, previous } => {
+                    restore(&mut self.code, address, previous)
+                }
+                JournalEntry::AccountCreated { address } => {
+                    self.created.remove(&address);
+                }
+                JournalEntry::AccountDestroyed { address } => {
+                    self.destroyed.remove(&address);
+                }
+                JournalEntry::AddressWarmed { address } => {
+                    self.accessed_addresses.remove(&address);
+                }
+                JournalEntry::SlotWarmed { address, key } => {
+                    self.accessed_slots.remove(&(address, key));
+                }
+                JournalEntry::LogEmitted => {
+                    self.logs.pop();
+                }
+            }
+        }
+    }
+
+    pub fn sload(&self, address: Address, key: U256) -> U256 {
+        match self.storage.get(&(address, key)) {
+            Some(value) => *value,
+            None => self.db.sload(address, key),
+        }
+    }
+
+    /// value of the slot when the transaction started
+    pub fn original(&self, address: Address, key: U256) -> U256 {
+        self.db.sload(address, key)
+    }
+
+    pub fn sstore(&mut self, address: Address, key: U256, value: U256) {
+        let previous = self.storage.insert((address, key), value);
+        self.entries.push(JournalEntry::StorageChanged {
+            address,
+            key,
+            previous,
+        });
+    }
+
+    pub fn tload(&self, address: Address, key: U256) -> U256 {
+        self.transient
+            .get(&(address, key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn tstore(&mut self, address: Address, key: U256, value: U256) {
+        let previous = self.transient.insert((address, key), value);
+        self.entries.push(JournalEntry::TransientChanged {
+            address,
+            key,
+            previous,
+        });
+    }
+
+    pub fn code(&self, address: Address) -> Vec<u8> {
+        match self.code.get(&address) {
+            Some(code) => code.clone(),
+            None => self.db.code(address),
+        }
+    }
+
+    pub fn set_code(&mut self, address: Address, code: Vec<u8>) {
+        let previous = self.code.insert(address, code);
+        self.entries
+            .push(JournalEntry::CodeChanged { address, previous });
+    }
+
+    pub fn code_hash(&self, address: Address) -> H256 {
+        match self.code.get(&address) {
+            Some(code) => code_hash(code),
+            None => self.db.code_hash(address),
+        }
+    }
+
+    pub fn nonce(&self, address: Address) -> u64 {
+        match self.nonces.get(&address) {
+            Some(nonce) => *nonce,
+            None => self.db.nonce(address),
+        }
+    }
+
+    pub fn set_nonce(&mut self, address: Address, nonce: u64) {
+        let previous = self.nonces.insert(address, nonce);
+        self.entries
+            .push(JournalEntry::NonceChanged { address, previous });
+    }
+
+    pub fn balance(&self, address: Address) -> U256 {
+        match self.balances.get(&address) {
+            Some(balance) => *balance,
+            None => self.db.balance(address),
+        }
+    }
+
+    pub fn set_balance(&mut self, address: Address, balance: U256) {
+        let previous = self.balances.insert(address, balance);
+        self.entries
+            .push(JournalEntry::BalanceChanged { address, previous });
+    }
+
+    /// move `value` wei from `from` to `to`, which the caller has checked `from` can afford
+    pub fn transfer(&mut self, from: Address, to: Address, value: U256) {
+        if from == to || value.is_zero() {
+            return;
+        }
+
+        let balance = self.balance(from);
+        self.set_balance(from, balance - value);
+        let balance = self.balance(to);
+        self.set_balance(to, balance + value);
+    }
+
+    /// whether the account at `address` has no code, nonce or balance
+    pub fn is_empty(&self, address: Address) -> bool {
+        self.code(address).is_empty() && self.nonce(address) == 0 && self.balance(address).is_zero()
+    }
+
+    /// start a new contract at `address`
+    pub fn create_account(&mut self, address: Address) {
+        self.set_nonce(address, 1);
+        self.created.insert(address);
+        self.entries.push(JournalEntry::AccountCreated { address });
+    }
+
+    /// whether `address` was created earlier in this transaction
+    pub fn is_created(&self, address: Address) -> bool {
+        self.created.contains(&address)
+    }
+
+    /// delete the account at `address` once the transaction is over,
+    /// returning `false` if it was already going to be
+    pub fn destroy_account(&mut self, address: Address) -> bool {
+        if !self.destroyed.insert(address) {
+            return false;
+        }
+
+        self.entries
+            .push(JournalEntry::AccountDestroyed { address });
+        true
+    }
+
+    /// mark `address` as accessed, returning whether this is the first access (it was cold)
+    pub fn access_address(&mut self, address: Address) -> bool {
+        if !self.accessed_addresses.insert(address) {
+            return false;
+        }
+
+        self.entries.push(JournalEntry::AddressWarmed { address });
+        true
+    }
+
+    /// mark slot `key` of `address` as accessed, returning whether it was cold
+    pub fn access_slot(&mut self, address: Address, key: U256) -> bool {
+        if !self.accessed_slots.insert((address, key)) {
+            return false;
+        }
+
+        self.entries.push(JournalEntry::SlotWarmed { address, key });
+        true
+    }
+
+    pub fn log(&mut self, log: Log) {
+        self.logs.push(log);
+        self.entries.push(JournalEntry::LogEmitted);
+    }
+
+    /// logs emitted so far that haven't been reverted
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// write every change through to the backend, returning the logs emitted
+    ///
+    /// transient storage is dropped rather than written
+    pub fn apply(self) -> Vec<Log> {
+        for ((address, key), value) in self.storage {
+            self.db.sstore(address, key, value);
+        }
+        for (address, balance) in self.balances {
+            self.db.set_balance(address, balance);
+        }
+        for (address, nonce) in self.nonces {
+            self.db.set_nonce(address, nonce);
+        }
+        for (address, code) in self.code {
+            self.db.set_code(address, code);
+        }
+        for address in self.destroyed {
+            self.db.delete_account(address);
+        }
+        self.logs
+    }
+}
+
+// put back the value an entry had before it was changed
+fn restore<K: std::hash::Hash + Eq, V>(map: &mut HashMap<K, V>, key: K, previous: Option<V>) {
+    match previous {
+        Some(value) => map.insert(key, value),
+        None => map.remove(&key),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Journal;
+    use crate::log::Log;
+    use crate::tests::{run_with_callee, test_env, CALLEE, CONTRACT};
+    use crate::{Address, Database, EvmResult, InMemoryDb};
+    use primitive_types::U256;
+
+    fn address(value: u64) -> Address {
+        Address::from_low_u64_be(value)
+    }
+
+    // run `bin` with the contract holding 100 wei
+    fn run(bin: &str, callee: &str, db: &mut InMemoryDb) -> EvmResult {
+        db.set_balance(address(CONTRACT), 100.into());
+        run_with_callee(bin, callee, &test_env(), db)
+    }
+
+    #[test]
+    fn revert_to() {
+        let mut db = InMemoryDb::new();
+        db.set_balance(address(1), 10.into());
+        let mut journal = Journal::new(&mut db);
+
+        journal.sstore(address(1), 0.into(), 1.into());
+        let outer = journal.checkpoint();
+        journal.sstore(address(1), 0.into(), 2.into());
+        journal.transfer(address(1), address(2), 4.into());
+
+        let inner = journal.checkpoint();
+        journal.set_nonce(address(1), 5);
+        journal.create_account(address(3));
+        journal.set_code(address(3), vec![0x00]);
+        assert!(journal.destroy_account(address(1)));
+        assert!(!journal.destroy_account(address(1)));
+        journal.log(Log {
+            address: address(3),
+            topics: Vec::new(),
+            data: Vec::new(),
+        });
+        journal.revert_to(inner);
+
+        assert_eq!(journal.nonce(address(1)), 0);
+        assert_eq!(journal.nonce(address(3)), 0);
+        assert!(journal.code(address(3)).is_empty());
+        assert!(!journal.is_created(address(3)));
+        assert!(journal.destroyed.is_empty());
+        assert!(journal.logs.is_empty());
+        assert_eq!(journal.balance(address(2)), 4.into());
+
+        journal.commit(outer);
+        assert_eq!(journal.sload(address(1), 0.into()), 2.into());
+
+        journal.revert_to(outer);
+        assert_eq!(journal.sload(address(1), 0.into()), 1.into());
+        assert_eq!(journal.balance(address(1)), 10.into());
+        assert_eq!(journal.balance(address(2)), 0.into());
+
+        // only the changes left in the journal reach the database
+        assert!(journal.apply().is_empty());
+        assert_eq!(db.sload(address(1), 0.into()), 1.into());
+        assert_eq!(db.balance(address(1)), 10.into());
+        assert!(db.account(address(2)).is_none());
+    }
+
+    #[test]
+    fn failed_sub_call() {
+        // PUSH1 1\nPUSH1 0\nSSTORE\nCALL 0xbeef
+        let bin = "60016000556000600060006000600061beef5af1";
+
+        // PUSH1 2\nPUSH1 0\nSSTORE
+        let mut db = InMemoryDb::new();
+        let result = run(bin, "6002600055", &mut db);
+        assert_eq!(result.stack, vec![1.into()]);
+        assert_eq!(db.sload(address(CONTRACT), 0.into()), 1.into());
+        assert_eq!(db.sload(address(CALLEE), 0.into()), 2.into());
+
+        // the same followed by REVERT or INVALID only undoes the callee's write
+        for callee in ["600260005560006000fd", "6002600055fe"] {
+            let mut db = InMemoryDb::new();
+            let result = run(bin, callee, &mut db);
+            assert!(result.success);
+            assert_eq!(result.stack, vec![0.into()]);
+            assert_eq!(db.sload(address(CONTRACT), 0.into()), 1.into());
+            assert!(db.sload(address(CALLEE), 0.into()).is_zero());
+        }
+    }
+
+    #[test]
+    fn value_transfer() {
+        // CALL 0xbeef with a value of 30
+        let bin = "6000600060006000601e61beef5af1";
+
+        let mut db = InMemoryDb::new();
+        let result = run(bin, "", &mut db);
+        assert_eq!(result.stack, vec![1.into()]);
+        assert_eq!(db.balance(address(CONTRACT)), 70.into());
+        assert_eq!(db.balance(address(CALLEE)), 30.into());
+
+        // a reverted call gives the value back
+        // PUSH1 0\nPUSH1 0\nREVERT
+        let mut db = InMemoryDb::new();
+        let result = run(bin, "60006000fd", &mut db);
+        assert_eq!(result.stack, vec![0.into()]);
+        assert_eq!(db.balance(address(CONTRACT)), 100.into());
+        assert!(db.balance(address(CALLEE)).is_zero());
+    }
+
+    #[test]
+    fn insufficient_balance() {
+        let mut db = InMemoryDb::new();
+
+        // CALL 0xbeef with a value of 200\nthe callee would run PUSH1 1\nPUSH1 0\nSSTORE
+        let result = run("600060006000600060c861beef5af1", "6001600055", &mut db);
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into()]);
+        assert_eq!(db.balance(address(CONTRACT)), U256::from(100));
+        assert!(db.sload(address(CALLEE), 0.into()).is_zero());
+    }
+}
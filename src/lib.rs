@@ -1,172 +1,601 @@
-use crate::utils::ValidJumps;
-use primitive_types::U256;
-use std::collections::HashMap;
+use crate::call::{CallRequest, FrameOutcome, CALL_DEPTH_LIMIT};
+use crate::gas::Gas;
+use crate::instructions::INSTRUCTIONS;
+use crate::journal::{Checkpoint, Journal};
+use crate::opcode::SpecRules;
+use crate::state::code_hash;
+use primitive_types::{H160, U256};
+use std::rc::Rc;
 
 mod arithmetic;
+mod block;
+mod bytecode;
+mod call;
 mod cmp;
+mod create;
+mod db;
 mod dup_swap;
+mod env;
 mod flow;
+mod gas;
 mod hash;
+mod inspector;
+mod instructions;
+mod journal;
+mod log;
 mod memory;
+mod opcode;
+mod precompile;
+mod spec;
+mod stack;
+mod state;
+mod storage;
+mod system;
 mod utils;
 
+/// Why the interpreter stopped executing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaltReason {
+    /// `STOP` was executed or execution ran past the end of the code
+    Stop,
+    /// `RETURN` was executed
+    Return,
+    /// `REVERT` was executed
+    Revert,
+    /// `SELFDESTRUCT` was executed
+    SelfDestruct,
+    /// the opcode is undefined or explicitly `INVALID`
+    InvalidOpcode,
+    /// an opcode needed more items than the stack holds
+    StackUnderflow,
+    /// an opcode pushed past the maximum stack depth
+    StackOverflow,
+    /// a jump targeted something other than a `JUMPDEST`
+    InvalidJump,
+    /// execution ran out of gas
+    OutOfGas,
+    /// state was modified inside a `STATICCALL`
+    StateChangeDuringStaticCall,
+    /// `RETURNDATACOPY` read past the end of the return data
+    ReturnDataOutOfBounds,
+    /// init code returned more code than can be deployed
+    CodeSizeLimit,
+    /// init code returned code starting with the reserved `0xef` byte
+    InvalidCodePrefix,
+    /// a contract already exists at the address being created
+    CreateCollision,
+    /// a sub call was made past the maximum call depth
+    CallDepthExceeded,
+    /// a sub call sent more value than the sender holds
+    InsufficientBalance,
+    /// a precompile was given input it can't handle
+    PrecompileError,
+    /// the creator's nonce can't go any higher (EIP-2681)
+    NonceOverflow,
+}
+
+impl HaltReason {
+    /// whether execution ended normally
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self,
+            HaltReason::Stop | HaltReason::Return | HaltReason::SelfDestruct
+        )
+    }
+}
+
+pub use crate::block::BlockEnv;
+pub use crate::bytecode::{AnalyzedBytecode, BytecodeCache};
+pub use crate::db::{Database, InMemoryDb};
+pub use crate::env::{CallContext, Env};
+pub use crate::gas::DEFAULT_GAS_LIMIT;
+pub use crate::inspector::{Inspector, Step};
+pub use crate::log::Log;
+pub use crate::memory::Memory;
+pub use crate::opcode::{disassemble, opcode_info, opcode_name, OpcodeInfo};
+pub use crate::precompile::{Precompile, PrecompileOutput, PrecompileResult, Precompiles};
+pub use crate::spec::SpecId;
+pub use crate::stack::Stack;
+pub use crate::state::Account;
+
+/// a 20 byte account address
+pub type Address = H160;
+
 pub struct EvmResult {
     pub stack: Vec<U256>,
     pub success: bool,
+    pub reason: HaltReason,
+    /// program counter of the instruction that halted execution
+    pub pc: usize,
+    /// opcode of the instruction that halted execution
+    pub opcode: u8,
+    pub gas_used: u64,
+    pub gas_left: u64,
+    /// output of `RETURN` or `REVERT`, empty for any other halt
+    pub return_data: Vec<u8>,
+    /// events emitted during execution, empty unless it succeeded
+    pub logs: Vec<Log>,
+    /// gas to be refunded to the sender, zero unless execution succeeded
+    pub gas_refunded: u64,
 }
 
 // opcode aliases
 const STOP: u8 = 0x00;
-const POP: u8 = 0x50;
-const PUSH0: u8 = 0x5f;
 const PUSH1: u8 = 0x60;
 const PUSH32: u8 = 0x7f;
-const INVALID: u8 = 0xfe;
-const PC: u8 = 0x58;
 
-struct Program<'a> {
-    code: &'a [u8],
+// a single call frame
+struct Program {
+    code: Rc<AnalyzedBytecode>,
+    context: CallContext,
     pc: usize,
+    gas: Gas,
     state: ProgramState,
+    output: Vec<u8>,
+    // output of the most recent sub call
+    return_data: Vec<u8>,
+    // sub call waiting to be started
+    call: Option<CallRequest>,
+    // where our output goes in the caller's memory
+    ret_offset: U256,
+    ret_size: U256,
+    // account being deployed when this frame runs init code
+    created: Option<Address>,
+    // where the journal stood when this frame started
+    checkpoint: Checkpoint,
 }
 
-impl<'a> Program<'a> {
-    fn new<A: AsRef<[u8]> + 'a>(_code: &'a A) -> Self {
-        let code = _code.as_ref();
+impl Program {
+    fn new(code: Rc<AnalyzedBytecode>, context: CallContext, gas_limit: u64) -> Self {
         Self {
             code,
+            context,
             pc: 0,
+            gas: Gas::new(gas_limit),
             state: ProgramState::new(),
+            output: Vec::new(),
+            return_data: Vec::new(),
+            call: None,
+            ret_offset: U256::zero(),
+            ret_size: U256::zero(),
+            created: None,
+            checkpoint: Checkpoint::default(),
+        }
+    }
+
+    // the frame as an inspector sees it around the instruction at `pc`
+    fn snapshot(&self, depth: usize, pc: usize, opcode: u8) -> Step<'_> {
+        Step {
+            depth,
+            pc,
+            opcode,
+            address: self.context.address,
+            gas_left: self.gas.remaining(),
+            stack: &self.state.stack,
+            memory: &self.state.memory,
         }
     }
 }
 
-struct ProgramState {
-    stack: Vec<U256>,
-    memory: Memory,
+// everything shared by the frames of a transaction
+struct Host<'a> {
+    env: &'a Env,
+    journal: Journal<'a>,
+    // code already analyzed by an earlier frame or transaction
+    bytecode: &'a mut BytecodeCache,
+    // availability, stack requirements and static gas of each opcode under `env.spec`
+    rules: &'static SpecRules,
+    inspector: Option<&'a mut dyn Inspector>,
 }
 
-pub struct Memory {
-    pub data: HashMap<U256, u8>,
-    pub size: U256,
+struct ProgramState {
+    stack: Stack,
+    memory: Memory,
 }
 
 impl ProgramState {
     fn new() -> Self {
         Self {
-            stack: Vec::new(),
-            memory: Memory {
-                data: HashMap::new(),
-                size: U256::zero(),
-            },
+            stack: Stack::new(),
+            memory: Memory::new(),
         }
     }
 }
 
 pub fn evm(_code: impl AsRef<[u8]>) -> EvmResult {
-    let mut program = Program::new(&_code);
+    evm_with_gas(_code, DEFAULT_GAS_LIMIT)
+}
 
-    // get all the valid jump destinations up front
-    let jumps = ValidJumps::new(program.code).jumps;
+pub fn evm_with_gas(_code: impl AsRef<[u8]>, gas_limit: u64) -> EvmResult {
+    let mut db = InMemoryDb::new();
+    evm_with_storage(_code, gas_limit, Address::zero(), &mut db)
+}
 
-    while program.pc < program.code.len() {
-        let opcode = program.code[program.pc];
-        program.pc += 1;
+/// run `_code` as the contract at `address`, reading and writing slots in `db`
+pub fn evm_with_storage(
+    _code: impl AsRef<[u8]>,
+    gas_limit: u64,
+    address: Address,
+    db: &mut dyn Database,
+) -> EvmResult {
+    let env = Env {
+        address,
+        gas_limit,
+        ..Env::default()
+    };
+    execute(_code, &env, db)
+}
 
-        // push byte value onto the stack
-        if opcode >= PUSH1 && opcode <= PUSH32 {
-            let size = (opcode - PUSH1 + 1) as usize;
-            program.state.stack.push(U256::from_big_endian(
-                &program.code[program.pc..program.pc + size],
-            ));
-            program.pc += size;
-            continue;
-        }
+/// run the code deployed at `env.address` in the context described by `env`
+pub fn transact(env: &Env, db: &mut dyn Database) -> EvmResult {
+    transact_with_cache(env, db, &mut BytecodeCache::new())
+}
 
-        // arithmetic operations
-        if opcode >= 0x01 && opcode <= 0x0b {
-            arithmetic::exec(opcode, &mut program.state.stack);
-            continue;
-        }
+/// run `transact` reusing the code analyzed by earlier transactions in `cache`, and adding to it
+pub fn transact_with_cache(
+    env: &Env,
+    db: &mut dyn Database,
+    cache: &mut BytecodeCache,
+) -> EvmResult {
+    let code = cache.get_or_analyze(db.code_hash(env.address), || db.code(env.address));
+    run_transaction(code, env, db, cache, None)
+}
+
+/// run `_code` in the call context described by `env`
+pub fn execute(_code: impl AsRef<[u8]>, env: &Env, db: &mut dyn Database) -> EvmResult {
+    // nothing has been cached yet, so there's no point hashing the code to look it up
+    let code = Rc::new(AnalyzedBytecode::new(_code.as_ref().to_vec()));
+    run_transaction(code, env, db, &mut BytecodeCache::new(), None)
+}
+
+/// run `execute` reusing the code analyzed by earlier calls in `cache`, and adding to it
+pub fn execute_with_cache(
+    _code: impl AsRef<[u8]>,
+    env: &Env,
+    db: &mut dyn Database,
+    cache: &mut BytecodeCache,
+) -> EvmResult {
+    let code = _code.as_ref();
+    let code = cache.get_or_analyze(code_hash(code), || code.to_vec());
+    run_transaction(code, env, db, cache, None)
+}
+
+/// run `_code` like `execute`, reporting each step, call and log to `inspector`
+pub fn execute_with_inspector(
+    _code: impl AsRef<[u8]>,
+    env: &Env,
+    db: &mut dyn Database,
+    inspector: &mut dyn Inspector,
+) -> EvmResult {
+    let code = Rc::new(AnalyzedBytecode::new(_code.as_ref().to_vec()));
+    run_transaction(code, env, db, &mut BytecodeCache::new(), Some(inspector))
+}
 
-        // comparison operations
-        if opcode >= 0x10 && opcode < 0x20 {
-            cmp::exec(opcode, &mut program.state.stack);
-            continue;
+fn run_transaction<'a>(
+    code: Rc<AnalyzedBytecode>,
+    env: &'a Env,
+    db: &'a mut dyn Database,
+    bytecode: &'a mut BytecodeCache,
+    inspector: Option<&'a mut dyn Inspector>,
+) -> EvmResult {
+    let mut host = Host {
+        env,
+        journal: Journal::new(db),
+        bytecode,
+        rules: opcode::spec_rules(env.spec),
+        inspector,
+    };
+    let mut root = Program::new(code, CallContext::new(env), env.gas_limit);
+
+    // accounts the transaction touches anyway start warm (EIP-2929),
+    // along with whatever it lists up front (EIP-2930)
+    host.journal.access_address(env.origin);
+    host.journal.access_address(env.caller);
+    host.journal.access_address(env.address);
+    for address in env.precompiles.addresses(env.spec) {
+        host.journal.access_address(address);
+    }
+    if env.spec.is_enabled_in(SpecId::Shanghai) {
+        host.journal.access_address(env.block.coinbase);
+    }
+    for (address, keys) in &env.access_list {
+        host.journal.access_address(*address);
+        for key in keys {
+            host.journal.access_slot(*address, *key);
+        }
+    }
+    root.checkpoint = host.journal.checkpoint();
+
+    let mut frames = vec![root];
+    loop {
+        let depth = frames.len();
+        let program = frames.last_mut().unwrap();
+        let reason = match run(program, &mut host, depth - 1) {
+            Some(reason) => reason,
+            None => {
+                // the frame stopped to make a sub call
+                let request = program.call.take().unwrap();
+                if let Some(child) = start_frame(program, &mut host, request, depth) {
+                    frames.push(child);
+                }
+                continue;
+            }
+        };
+
+        let mut child = frames.pop().unwrap();
+        let mut reason = reason;
+        if let Some(address) = child.created {
+            if reason.is_success() {
+                // the output of init code is the code to deploy
+                match create::deposit_code(&mut child.gas, &child.output, host.env.spec) {
+                    Ok(()) => host.journal.set_code(address, child.output.clone()),
+                    Err(error) => reason = error,
+                }
+            }
+        }
+        if !reason.is_success() && reason != HaltReason::Revert {
+            child.gas.consume_all();
         }
 
-        // dup and swap operations
-        if opcode >= 0x80 && opcode <= 0x9f {
-            dup_swap::exec(opcode, &mut program.state.stack);
-            continue;
+        // a failed frame leaves no trace in the state
+        if reason.is_success() {
+            host.journal.commit(child.checkpoint);
+        } else {
+            host.journal.revert_to(child.checkpoint);
         }
 
-        // control flow opcodes
-        if opcode == 0x56 || opcode == 0x57 || opcode == 0x5b {
-            let result = flow::exec(
-                opcode,
-                &mut program.state.stack,
-                &mut program.pc,
-                program.code,
-                &jumps,
-            );
-            // control flow opcodes can terminate the program
-            if let Some(result) = result {
-                return result;
+        let parent = match frames.last_mut() {
+            Some(parent) => parent,
+            None => {
+                // state changes and logs only stick if execution succeeded
+                let logs = if reason.is_success() {
+                    host.journal.apply()
+                } else {
+                    Vec::new()
+                };
+
+                return EvmResult {
+                    success: reason.is_success(),
+                    reason,
+                    pc: child.pc,
+                    opcode: child.code.padded_code()[child.pc],
+                    gas_used: child.gas.used(),
+                    gas_left: child.gas.remaining(),
+                    return_data: child.output,
+                    stack: child.state.stack.into_vec(),
+                    logs,
+                    gas_refunded: if reason.is_success() {
+                        child.gas.final_refund(host.env.spec)
+                    } else {
+                        0
+                    },
+                };
             }
-            continue;
+        };
+
+        // refunds only count if the frame that earned them succeeded
+        if reason.is_success() {
+            parent.gas.record_refund(child.gas.refunded());
         }
 
-        // memory opcodes
-        if (opcode >= 0x51 && opcode <= 0x53) || opcode == 0x59 {
-            let result = memory::exec(opcode, &mut program.state.stack, &mut program.state.memory);
-            if let Some(result) = result {
-                return result;
-            }
-            continue;
+        let outcome = FrameOutcome {
+            reason,
+            output: child.output,
+            gas_left: child.gas.remaining(),
+            created: child.created,
+            ret_offset: child.ret_offset,
+            ret_size: child.ret_size,
+        };
+        finish_frame(parent, &mut host, outcome);
+    }
+}
+
+// set up the frame for a sub call, or returns `None` when it fails before running anything
+fn start_frame(
+    program: &mut Program,
+    host: &mut Host,
+    request: CallRequest,
+    depth: usize,
+) -> Option<Program> {
+    let created = request.init_code.is_some().then_some(request.code_address);
+    if let Some(inspector) = host.inspector.as_deref_mut() {
+        match &request.init_code {
+            Some(init_code) => inspector.create(&request.context, init_code, request.gas_limit),
+            None => inspector.call(&request.context, request.code_address, request.gas_limit),
         }
+    }
+
+    // fail the call without running anything, handing back the gas it was given
+    let reason = if depth > CALL_DEPTH_LIMIT {
+        Some(HaltReason::CallDepthExceeded)
+    } else if host.journal.balance(request.context.caller) < request.transfer {
+        Some(HaltReason::InsufficientBalance)
+    } else if created.is_some() && host.journal.nonce(program.context.address) == u64::MAX {
+        Some(HaltReason::NonceOverflow)
+    } else {
+        None
+    };
+    if let Some(reason) = reason {
+        let outcome = request.outcome(reason, Vec::new(), request.gas_limit);
+        finish_frame(program, host, outcome);
+        return None;
+    }
 
-        // hash opcodes
-        if opcode == 0x20 {
-            hash::exec(opcode, &mut program.state.stack, &mut program.state.memory);
+    if created.is_some() {
+        // the creator's nonce goes up even if the creation fails
+        let creator = program.context.address;
+        let nonce = host.journal.nonce(creator);
+        host.journal.set_nonce(creator, nonce + 1);
+    }
+
+    // a new contract is warm even if creating it fails
+    if let Some(address) = created {
+        host.journal.access_address(address);
+    }
+
+    let checkpoint = host.journal.checkpoint();
+    if let Some(address) = created {
+        if host.journal.nonce(address) != 0 || !host.journal.code(address).is_empty() {
+            // a collision burns all the gas passed on
+            let outcome = request.outcome(HaltReason::CreateCollision, Vec::new(), 0);
+            finish_frame(program, host, outcome);
+            return None;
         }
+        host.journal.create_account(address);
+    }
 
-        // basic opcodes
-        match opcode {
-            STOP => {
-                return EvmResult {
-                    stack: program.state.stack,
-                    success: true,
-                }
-            }
-            PUSH0 => program.state.stack.push(U256::zero()),
-            POP => {
-                program.state.stack.pop();
-            }
-            PC => {
-                program.state.stack.push(U256::from(program.pc - 1));
-            }
-            INVALID => {
-                return EvmResult {
-                    stack: program.state.stack,
-                    success: false,
+    let context = &request.context;
+    host.journal
+        .transfer(context.caller, context.address, request.transfer);
+
+    // precompiles run natively instead of getting a frame of their own
+    let precompile = match created {
+        Some(_) => None,
+        None => host
+            .env
+            .precompiles
+            .get(request.code_address, host.env.spec),
+    };
+    if let Some(precompile) = precompile {
+        let result =
+            precompile(&context.calldata, request.gas_limit, host.env.spec).and_then(|result| {
+                // a custom precompile might claim more gas than it was given
+                if result.gas_used > request.gas_limit {
+                    Err(HaltReason::OutOfGas)
+                } else {
+                    Ok(result)
                 }
+            });
+        let (output, gas_left, reason) = match result {
+            Ok(result) => (
+                result.output,
+                request.gas_limit - result.gas_used,
+                HaltReason::Return,
+            ),
+            Err(reason) => {
+                host.journal.revert_to(checkpoint);
+                (Vec::new(), 0, reason)
             }
-            _ => (),
-        }
+        };
+        let outcome = request.outcome(reason, output, gas_left);
+        finish_frame(program, host, outcome);
+        return None;
     }
 
-    return EvmResult {
-        stack: program.state.stack,
-        success: true,
+    let code = match request.init_code {
+        Some(init_code) => host
+            .bytecode
+            .get_or_analyze(code_hash(&init_code), || init_code),
+        None => {
+            let address = request.code_address;
+            let hash = host.journal.code_hash(address);
+            host.bytecode
+                .get_or_analyze(hash, || host.journal.code(address))
+        }
     };
+    let mut child = Program::new(code, request.context, request.gas_limit);
+    child.ret_offset = request.ret_offset;
+    child.ret_size = request.ret_size;
+    child.created = created;
+    child.checkpoint = checkpoint;
+    Some(child)
+}
+
+// hand the outcome of a sub call back to the frame which made it
+fn finish_frame(parent: &mut Program, host: &mut Host, outcome: FrameOutcome) {
+    if let Some(inspector) = host.inspector.as_deref_mut() {
+        inspector.call_end(outcome.reason, &outcome.output, outcome.gas_left);
+    }
+
+    match outcome.created {
+        Some(address) => create::finish(
+            &mut parent.state.stack,
+            &mut parent.gas,
+            &mut parent.return_data,
+            address,
+            outcome,
+        ),
+        None => call::finish(
+            &mut parent.state.stack,
+            &mut parent.state.memory,
+            &mut parent.gas,
+            &mut parent.return_data,
+            outcome,
+        ),
+    }
+}
+
+// run a frame until it halts, or returns `None` when it stops to make a sub call
+fn run(program: &mut Program, host: &mut Host, depth: usize) -> Option<HaltReason> {
+    loop {
+        // running off the end of the code reaches the STOPs padding it
+        let pc = program.pc;
+        let opcode = program.code.padded_code()[pc];
+        if let Some(inspector) = host.inspector.as_deref_mut() {
+            inspector.step(&program.snapshot(depth, pc, opcode));
+        }
+        program.pc += 1;
+
+        let result = step(program, host, opcode);
+        if result.is_err() {
+            // point at the instruction which halted, not the one after it
+            program.pc = pc;
+        }
+        if let Some(inspector) = host.inspector.as_deref_mut() {
+            inspector.step_end(&program.snapshot(depth, pc, opcode), result.err());
+        }
+
+        if let Err(reason) = result {
+            return Some(reason);
+        }
+
+        if program.call.is_some() {
+            return None;
+        }
+    }
+}
+
+/// execute a single instruction, returning `Err` with the reason when execution must halt
+fn step(program: &mut Program, host: &mut Host, opcode: u8) -> Result<(), HaltReason> {
+    let rules = host.rules[opcode as usize].ok_or(HaltReason::InvalidOpcode)?;
+
+    // halt before running anything if the stack is too short or too full for the opcode
+    program
+        .state
+        .stack
+        .require(rules.inputs.into(), rules.outputs.into())?;
+    program.gas.record_cost(rules.gas)?;
+    INSTRUCTIONS[opcode as usize](program, host, opcode)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::evm;
+    use crate::{evm, execute, Address, Database, Env, EvmResult, HaltReason, InMemoryDb};
     use primitive_types::U256;
 
+    /// account the code under test runs as
+    pub const CONTRACT: u64 = 0xc0de;
+    /// account `run_with_callee` deploys its callee to
+    pub const CALLEE: u64 = 0xbeef;
+
+    // an environment running as `CONTRACT` with plenty of gas
+    pub fn test_env() -> Env {
+        Env {
+            address: Address::from_low_u64_be(CONTRACT),
+            gas_limit: 1_000_000,
+            ..Env::default()
+        }
+    }
+
+    // run `bin` in `env` with `callee` deployed at `CALLEE`
+    pub fn run_with_callee(bin: &str, callee: &str, env: &Env, db: &mut InMemoryDb) -> EvmResult {
+        db.set_code(
+            Address::from_low_u64_be(CALLEE),
+            hex::decode(callee).unwrap(),
+        );
+        execute(hex::decode(bin).unwrap(), env, db)
+    }
+
     pub struct TestSetup {
         asm: String,
         bin: String,
@@ -201,23 +630,13 @@ mod tests {
             expected_stack.push(U256::from_str_radix(value, 16).unwrap());
         }
 
-        let mut matching = result.stack.len() == expect_stack.len();
-        if matching {
-            for i in 0..result.stack.len() {
-                if result.stack[i] != expected_stack[i] {
-                    matching = false;
-                    break;
-                }
-            }
-        }
-
-        matching = matching && result.success == expect_success;
+        let matching = result.stack == expected_stack && result.success == expect_success;
 
         if !matching {
             println!("Instructions: \n{}\n", asm);
             println!("Expected success: {:?}", expect_success);
             println!("Actual success: {:?}", result.success);
-            println!("");
+            println!();
 
             println!("Expected stack: [");
             for v in expected_stack {
@@ -350,4 +769,54 @@ mod tests {
             run_test(setup);
         }
     }
+
+    #[test]
+    fn truncated_push() {
+        let setups = vec![
+            TestSetup::new("PUSH2 0xff", "61ff", vec!["0xff00"], true),
+            TestSetup::new("PUSH32", "7f", vec!["0x0"], true),
+        ];
+
+        for setup in setups {
+            run_test(setup);
+        }
+    }
+
+    #[test]
+    fn halt_reasons() {
+        // (bytecode, reason, pc, opcode)
+        let cases = vec![
+            ("00", HaltReason::Stop, 0, 0x00),
+            ("6001", HaltReason::Stop, 2, 0x00),
+            ("fe", HaltReason::InvalidOpcode, 0, 0xfe),
+            ("600101", HaltReason::StackUnderflow, 2, 0x01),
+            ("50", HaltReason::StackUnderflow, 0, 0x50),
+            ("6003566001", HaltReason::InvalidJump, 2, 0x56),
+            ("60ff56", HaltReason::InvalidJump, 2, 0x56),
+            ("80", HaltReason::StackUnderflow, 0, 0x80),
+            ("600191", HaltReason::StackUnderflow, 2, 0x91),
+            ("1e", HaltReason::InvalidOpcode, 0, 0x1e),
+            ("600160ff1b57", HaltReason::StackUnderflow, 5, 0x57),
+            (
+                "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff51",
+                HaltReason::OutOfGas,
+                33,
+                0x51,
+            ),
+            (
+                "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff600020",
+                HaltReason::OutOfGas,
+                35,
+                0x20,
+            ),
+        ];
+
+        for (bin, reason, pc, opcode) in cases {
+            let result = evm(hex::decode(bin).unwrap());
+            assert_eq!(result.reason, reason, "{}", bin);
+            assert_eq!(result.success, reason.is_success(), "{}", bin);
+            assert_eq!(result.pc, pc, "{}", bin);
+            assert_eq!(result.opcode, opcode, "{}", bin);
+        }
+    }
 }
@@ -0,0 +1,142 @@
+use crate::env::CallContext;
+use crate::gas::{self, Gas};
+use crate::journal::Journal;
+use crate::memory::mload_n;
+use crate::stack::Stack;
+use crate::{Address, HaltReason, Memory};
+use primitive_types::H256;
+
+// logging opcodes
+
+const LOG0: u8 = 0xa0;
+const LOG4: u8 = 0xa4;
+
+/// an event emitted by one of the `LOG` opcodes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    /// contract which emitted the event
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+pub fn exec(
+    opcode: u8,
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+    context: &CallContext,
+    journal: &mut Journal,
+) -> Result<(), HaltReason> {
+    match opcode {
+        LOG0..=LOG4 => {
+            if context.is_static {
+                return Err(HaltReason::StateChangeDuringStaticCall);
+            }
+
+            let offset = stack.pop()?;
+            let size = stack.pop()?;
+
+            let mut topics = Vec::new();
+            for _ in 0..opcode - LOG0 {
+                let mut topic = H256::zero();
+                stack.pop()?.to_big_endian(topic.as_bytes_mut());
+                topics.push(topic);
+            }
+
+            let data = mload_n(memory, gas, offset, size)?.to_vec();
+            gas.record_cost(gas::LOG_DATA * data.len() as u64)?;
+
+            journal.log(Log {
+                address: context.address,
+                topics,
+                data,
+            });
+            Ok(())
+        }
+        _ => unreachable!("Not a log opcode"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Log;
+    use crate::tests::{run_with_callee, test_env};
+    use crate::{evm, Address, EvmResult, InMemoryDb};
+    use primitive_types::H256;
+
+    fn topic(value: u64) -> H256 {
+        H256::from_low_u64_be(value)
+    }
+
+    fn run(bin: &str, callee: &str) -> EvmResult {
+        run_with_callee(bin, callee, &test_env(), &mut InMemoryDb::new())
+    }
+
+    #[test]
+    fn log0() {
+        // PUSH1 0x2a\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nLOG0
+        let result = evm(hex::decode("602a60005260206000a0").unwrap());
+        assert!(result.success);
+        assert_eq!(
+            result.logs,
+            vec![Log {
+                address: Address::zero(),
+                topics: Vec::new(),
+                data: hex::decode(
+                    "000000000000000000000000000000000000000000000000000000000000002a"
+                )
+                .unwrap(),
+            }]
+        );
+
+        // 375 for the log and 8 for each byte of data
+        assert_eq!(result.gas_used, 18 + 375 + 8 * 32);
+    }
+
+    #[test]
+    fn log4() {
+        // PUSH1 4\nPUSH1 3\nPUSH1 2\nPUSH1 1\nPUSH1 0\nPUSH1 0\nLOG4
+        let result = evm(hex::decode("600460036002600160006000a4").unwrap());
+        assert!(result.success);
+        assert_eq!(
+            result.logs[0].topics,
+            vec![topic(1), topic(2), topic(3), topic(4)]
+        );
+        assert!(result.logs[0].data.is_empty());
+        assert_eq!(result.gas_used, 18 + 5 * 375);
+    }
+
+    #[test]
+    fn revert() {
+        // PUSH1 0\nPUSH1 0\nLOG0\nPUSH1 0\nPUSH1 0\nREVERT
+        let result = evm(hex::decode("60006000a060006000fd").unwrap());
+        assert!(!result.success);
+        assert!(result.logs.is_empty());
+    }
+
+    #[test]
+    fn sub_calls() {
+        // PUSH1 1\nPUSH1 0\nPUSH1 0\nLOG1\nCALL 0xbeef\nPOP
+        let bin = "600160006000a16000600060006000600061beef5af150";
+        let logs = run(bin, "600260006000a1").logs;
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].address, Address::from_low_u64_be(0xc0de));
+        assert_eq!(logs[1].address, Address::from_low_u64_be(0xbeef));
+        assert_eq!(logs[1].topics, vec![topic(2)]);
+
+        // a reverted sub call drops only its own logs
+        let logs = run(bin, "600260006000a160006000fd").logs;
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].topics, vec![topic(1)]);
+    }
+
+    #[test]
+    fn staticcall() {
+        // STATICCALL 0xbeef\nwhich runs PUSH1 0\nPUSH1 0\nLOG0
+        let result = run("600060006000600061beef5afa", "60006000a0");
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into()]);
+        assert!(result.logs.is_empty());
+    }
+}
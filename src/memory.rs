@@ -1,97 +1,231 @@
-use crate::utils::Stack;
-use crate::{EvmResult, Memory};
+use crate::gas::{self, Gas};
+use crate::stack::Stack;
+use crate::HaltReason;
 use primitive_types::U256;
 
-/// memory opcodes
+// memory opcodes
 
 const MLOAD: u8 = 0x51;
 const MSTORE: u8 = 0x52;
 const MSTORE8: u8 = 0x53;
 const MSIZE: u8 = 0x59;
+const MCOPY: u8 = 0x5e;
 
-fn set_msize(memory: &mut Memory, mut highest_access: U256) {
-    let rem = highest_access
-        .checked_rem(U256::from(32))
-        .unwrap_or(U256::zero());
-    if rem != U256::zero() {
-        let add: U256 = U256::from(32) - rem;
-        highest_access += add;
+// accesses past this point could never be paid for, so treat them as running out of gas
+const MAX_MEMORY: u64 = u32::MAX as u64;
+
+/// the byte addressed scratch space of a call frame, grown a word at a time
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    data: Vec<u8>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// bytes allocated so far, always a whole number of words
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// zero fill memory up to `size` bytes, it never shrinks
+    pub fn resize(&mut self, size: usize) {
+        if size > self.data.len() {
+            self.data.resize(size, 0);
+        }
+    }
+
+    /// the `size` bytes from `offset`, which have to be allocated already
+    pub fn slice(&self, offset: usize, size: usize) -> &[u8] {
+        &self.data[offset..offset + size]
+    }
+
+    /// overwrite the bytes from `offset`, which have to be allocated already
+    pub fn set(&mut self, offset: usize, bytes: &[u8]) {
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// write `size` bytes of `data` from `data_offset` to `offset`, zero filling past its end
+    pub fn set_data(&mut self, offset: usize, data: &[u8], data_offset: usize, size: usize) {
+        let available = data.len().saturating_sub(data_offset).min(size);
+        let (copied, zeroed) = self.data[offset..offset + size].split_at_mut(available);
+        copied.copy_from_slice(&data[data_offset..data_offset + available]);
+        zeroed.fill(0);
+    }
+
+    /// copy `size` bytes from `src` to `dest`, which may overlap
+    pub fn copy_within(&mut self, src: usize, dest: usize, size: usize) {
+        self.data.copy_within(src..src + size, dest);
+    }
+}
+
+// get the first byte past the accessed region, failing if it's unreasonably large
+fn access_end(offset: U256, size: U256) -> Result<u64, HaltReason> {
+    match offset.checked_add(size) {
+        Some(end) if end <= MAX_MEMORY.into() => Ok(end.as_u64()),
+        _ => Err(HaltReason::OutOfGas),
+    }
+}
+
+/// grow memory to cover `size` bytes from `offset`, charging for every new word
+pub fn expand(
+    memory: &mut Memory,
+    gas: &mut Gas,
+    offset: U256,
+    size: U256,
+) -> Result<(), HaltReason> {
+    // zero sized accesses never touch memory, whatever the offset
+    if size.is_zero() {
+        return Ok(());
+    }
+
+    let new_size = gas::words(access_end(offset, size)?) * 32;
+    let old_size = memory.len() as u64;
+    if new_size > old_size {
+        gas.record_cost(gas::memory_cost(new_size) - gas::memory_cost(old_size))?;
+        memory.resize(new_size as usize);
     }
-    memory.size = highest_access;
+
+    Ok(())
 }
 
-fn mstore(memory: &mut Memory, offset: U256, value: U256) {
+fn mstore(memory: &mut Memory, gas: &mut Gas, offset: U256, value: U256) -> Result<(), HaltReason> {
     let bytes = &mut [0; 32];
     value.to_big_endian(bytes);
-    let mut idx = U256::zero();
-    for (i, byte) in bytes.iter().enumerate() {
-        idx = offset + U256::from(i);
-        let addr = memory.data.entry(idx).or_insert(0);
-        *addr = *byte;
-    }
 
-    set_msize(memory, idx);
+    mstore_n(memory, gas, offset, bytes)
 }
 
-pub fn mload_n(memory: &mut Memory, mut offset: U256, size: U256) -> Vec<u8> {
-    // by default allocate one word to the return array
-    let mut bytes: Vec<u8> = Vec::with_capacity(32);
+/// write `bytes` into memory starting at `offset`
+pub fn mstore_n(
+    memory: &mut Memory,
+    gas: &mut Gas,
+    offset: U256,
+    bytes: &[u8],
+) -> Result<(), HaltReason> {
+    expand(memory, gas, offset, bytes.len().into())?;
+    write(memory, offset, bytes);
 
-    let top_byte = offset
-        .checked_add(size)
-        .expect("memory access out of bounds");
+    Ok(())
+}
 
-    while offset < top_byte {
-        bytes.push(*memory.data.get(&offset).unwrap_or(&0));
-        offset += 1.into();
+/// write `bytes` into memory that has already been expanded and paid for
+pub fn write(memory: &mut Memory, offset: U256, bytes: &[u8]) {
+    // an empty write can be anywhere, even past what's allocated
+    if !bytes.is_empty() {
+        memory.set(offset.as_usize(), bytes);
     }
+}
 
-    println!("top byte = {}", top_byte);
-    set_msize(memory, offset);
+/// read `size` bytes from `offset`, growing memory to cover them first
+pub fn mload_n<'a>(
+    memory: &'a mut Memory,
+    gas: &mut Gas,
+    offset: U256,
+    size: U256,
+) -> Result<&'a [u8], HaltReason> {
+    if size.is_zero() {
+        return Ok(&[]);
+    }
+    expand(memory, gas, offset, size)?;
 
-    bytes
+    Ok(memory.slice(offset.as_usize(), size.as_usize()))
 }
 
-fn mload(memory: &mut Memory, offset: U256) -> U256 {
-    let bytes = mload_n(memory, offset, 32.into());
+fn mload(memory: &mut Memory, gas: &mut Gas, offset: U256) -> Result<U256, HaltReason> {
+    let bytes = mload_n(memory, gas, offset, 32.into())?;
 
-    U256::from_big_endian(&bytes)
+    Ok(U256::from_big_endian(bytes))
 }
 
-pub fn exec(opcode: u8, stack: &mut Vec<U256>, memory: &mut Memory) -> Option<EvmResult> {
+pub fn exec(
+    opcode: u8,
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+) -> Result<(), HaltReason> {
     match opcode {
         MSTORE => {
-            let offset = stack.safe_pop();
-            let value = stack.safe_pop();
+            let offset = stack.pop()?;
+            let value = stack.pop()?;
 
-            mstore(memory, offset, value);
+            mstore(memory, gas, offset, value)?;
         }
         MLOAD => {
-            let offset = stack.safe_pop();
+            let offset = stack.pop()?;
 
-            let value = mload(memory, offset);
-            stack.push(value);
+            let value = mload(memory, gas, offset)?;
+            stack.push(value)?;
         }
         MSTORE8 => {
-            let offset = stack.safe_pop();
-            let value = stack.safe_pop();
-            let addr = memory.data.entry(offset).or_insert(0);
-            *addr = value.byte(0);
-            set_msize(memory, offset);
+            let offset = stack.pop()?;
+            let value = stack.pop()?;
+            expand(memory, gas, offset, 1.into())?;
+
+            memory.set(offset.as_usize(), &[value.byte(0)]);
         }
         MSIZE => {
-            stack.push(memory.size);
+            stack.push(memory.len().into())?;
         }
-        _ => panic!("Not a memory opcode"),
+        MCOPY => {
+            let dest_offset = stack.pop()?;
+            let offset = stack.pop()?;
+            let size = stack.pop()?;
+
+            if size.is_zero() {
+                return Ok(());
+            }
+
+            // memory grows to cover both regions
+            expand(memory, gas, dest_offset, size)?;
+            expand(memory, gas, offset, size)?;
+            gas.record_cost(gas::copy_cost(size.as_u64()))?;
+
+            memory.copy_within(offset.as_usize(), dest_offset.as_usize(), size.as_usize());
+        }
+        _ => unreachable!("Not a memory opcode"),
     }
 
-    None
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use super::Memory;
+    use crate::evm;
     use crate::tests::{run_test, TestSetup};
 
+    #[test]
+    fn buffer() {
+        let mut memory = Memory::new();
+        assert!(memory.is_empty());
+
+        memory.resize(64);
+        memory.set(30, &[1, 2, 3, 4]);
+        assert_eq!(memory.len(), 64);
+        assert_eq!(memory.slice(29, 6), &[0, 1, 2, 3, 4, 0]);
+
+        // never shrinks
+        memory.resize(32);
+        assert_eq!(memory.len(), 64);
+
+        // overlapping copies see the source as it was before the copy
+        memory.copy_within(30, 31, 4);
+        assert_eq!(memory.slice(29, 6), &[0, 1, 1, 2, 3, 4]);
+
+        // reading past the end of the data fills with zeros
+        memory.set_data(0, &[5, 6, 7], 1, 4);
+        assert_eq!(memory.slice(0, 5), &[6, 7, 0, 0, 0]);
+        memory.set_data(0, &[5, 6, 7], 3, 2);
+        assert_eq!(memory.slice(0, 2), &[0, 0]);
+    }
+
     #[test]
     fn mstore() {
         let setups = vec![
@@ -141,10 +275,93 @@ mod tests {
                 vec!["0x100"],
                 true,
             ),
+            TestSetup::new(
+                "PUSH1 0xff\nPUSH1 0x20\nMSTORE8\nMSIZE",
+                "60ff60205359",
+                vec!["0x40"],
+                true,
+            ),
+            TestSetup::new(
+                "PUSH1 0x40\nMLOAD\nPOP\nPUSH1 0\nMLOAD\nPOP\nMSIZE",
+                "604051506000515059",
+                vec!["0x60"],
+                true,
+            ),
+        ];
+
+        for setup in setups {
+            run_test(setup);
+        }
+    }
+
+    #[test]
+    fn mcopy() {
+        let setups = vec![
+            TestSetup::new(
+                "PUSH32 0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nPUSH1 32\nMCOPY\nPUSH1 32\nMLOAD",
+                "7f000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f6000526020600060205e602051",
+                vec!["0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"],
+                true,
+            ),
+            TestSetup::new(
+                "PUSH32 0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nPUSH1 0\nMCOPY\nPUSH1 0\nMLOAD",
+                "7f000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f6000526020600060005e600051",
+                vec!["0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"],
+                true,
+            ),
+            // overlapping regions, copying backwards and forwards
+            TestSetup::new(
+                "PUSH32 0x0001020304050607080000000000000000000000000000000000000000000000\nPUSH1 0\nMSTORE\nPUSH1 8\nPUSH1 1\nPUSH1 0\nMCOPY\nPUSH1 0\nMLOAD",
+                "7f00010203040506070800000000000000000000000000000000000000000000006000526008600160005e600051",
+                vec!["0x0102030405060708080000000000000000000000000000000000000000000000"],
+                true,
+            ),
+            TestSetup::new(
+                "PUSH32 0x0001020304050607080000000000000000000000000000000000000000000000\nPUSH1 0\nMSTORE\nPUSH1 8\nPUSH1 0\nPUSH1 1\nMCOPY\nPUSH1 0\nMLOAD",
+                "7f00010203040506070800000000000000000000000000000000000000000000006000526008600060015e600051",
+                vec!["0x0000010203040506070000000000000000000000000000000000000000000000"],
+                true,
+            ),
         ];
 
         for setup in setups {
             run_test(setup);
         }
     }
+
+    #[test]
+    fn mcopy_msize() {
+        let setups = vec![
+            // the destination grows memory
+            TestSetup::new(
+                "PUSH1 32\nPUSH1 0\nPUSH1 0x40\nMCOPY\nMSIZE",
+                "6020600060405e59",
+                vec!["0x60"],
+                true,
+            ),
+            // and so does the source
+            TestSetup::new(
+                "PUSH1 32\nPUSH1 0x40\nPUSH1 0\nMCOPY\nMSIZE",
+                "6020604060005e59",
+                vec!["0x60"],
+                true,
+            ),
+            // copying nothing never touches memory
+            TestSetup::new(
+                "PUSH1 0\nPUSH32 0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\nDUP1\nMCOPY\nMSIZE",
+                "60007fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff805e59",
+                vec!["0x0"],
+                true,
+            ),
+        ];
+
+        for setup in setups {
+            run_test(setup);
+        }
+
+        // 3 for the copy and 3 for the memory on top of the static cost
+        // PUSH1 32\nPUSH1 0\nPUSH1 0\nMCOPY
+        let result = evm(hex::decode("6020600060005e").unwrap());
+        assert_eq!(result.gas_used, 3 * 3 + 3 + 3 + 3);
+    }
 }
@@ -0,0 +1,358 @@
+use crate::gas::{self, BASE, HIGH, JUMPDEST, LOW, MID, VERYLOW, ZERO};
+use crate::spec::SpecId::{self, *};
+use crate::{PUSH1, PUSH32};
+use std::sync::OnceLock;
+
+// table of the opcodes defined up to the latest fork
+
+#[derive(Debug, Clone, Copy)]
+pub struct OpcodeInfo {
+    pub name: &'static str,
+    /// items popped off the stack
+    pub inputs: u8,
+    /// items pushed onto the stack
+    pub outputs: u8,
+    /// fixed cost when the opcode was introduced, `gas::static_cost` has any later repricing
+    pub gas: u64,
+    /// fork that introduced the opcode
+    pub since: SpecId,
+}
+
+// an opcode that has been around since frontier
+const fn op(name: &'static str, inputs: u8, outputs: u8, gas: u64) -> OpcodeInfo {
+    OpcodeInfo {
+        name,
+        inputs,
+        outputs,
+        gas,
+        since: Frontier,
+    }
+}
+
+impl OpcodeInfo {
+    const fn since(self, since: SpecId) -> Self {
+        OpcodeInfo { since, ..self }
+    }
+}
+
+const fn lookup(opcode: u8) -> Option<OpcodeInfo> {
+    let info = match opcode {
+        0x00 => op("STOP", 0, 0, ZERO),
+        0x01 => op("ADD", 2, 1, VERYLOW),
+        0x02 => op("MUL", 2, 1, LOW),
+        0x03 => op("SUB", 2, 1, VERYLOW),
+        0x04 => op("DIV", 2, 1, LOW),
+        0x05 => op("SDIV", 2, 1, LOW),
+        0x06 => op("MOD", 2, 1, LOW),
+        0x07 => op("SMOD", 2, 1, LOW),
+        0x08 => op("ADDMOD", 3, 1, MID),
+        0x09 => op("MULMOD", 3, 1, MID),
+        0x0a => op("EXP", 2, 1, HIGH),
+        0x0b => op("SIGNEXTEND", 2, 1, LOW),
+        0x10 => op("LT", 2, 1, VERYLOW),
+        0x11 => op("GT", 2, 1, VERYLOW),
+        0x12 => op("SLT", 2, 1, VERYLOW),
+        0x13 => op("SGT", 2, 1, VERYLOW),
+        0x14 => op("EQ", 2, 1, VERYLOW),
+        0x15 => op("ISZERO", 1, 1, VERYLOW),
+        0x16 => op("AND", 2, 1, VERYLOW),
+        0x17 => op("OR", 2, 1, VERYLOW),
+        0x18 => op("XOR", 2, 1, VERYLOW),
+        0x19 => op("NOT", 1, 1, VERYLOW),
+        0x1a => op("BYTE", 2, 1, VERYLOW),
+        0x1b => op("SHL", 2, 1, VERYLOW).since(Constantinople),
+        0x1c => op("SHR", 2, 1, VERYLOW).since(Constantinople),
+        0x1d => op("SAR", 2, 1, VERYLOW).since(Constantinople),
+        0x20 => op("SHA3", 2, 1, 30),
+        0x30 => op("ADDRESS", 0, 1, BASE),
+        0x31 => op("BALANCE", 1, 1, 20),
+        0x32 => op("ORIGIN", 0, 1, BASE),
+        0x33 => op("CALLER", 0, 1, BASE),
+        0x34 => op("CALLVALUE", 0, 1, BASE),
+        0x35 => op("CALLDATALOAD", 1, 1, VERYLOW),
+        0x36 => op("CALLDATASIZE", 0, 1, BASE),
+        0x37 => op("CALLDATACOPY", 3, 0, VERYLOW),
+        0x38 => op("CODESIZE", 0, 1, BASE),
+        0x39 => op("CODECOPY", 3, 0, VERYLOW),
+        0x3a => op("GASPRICE", 0, 1, BASE),
+        0x3b => op("EXTCODESIZE", 1, 1, 20),
+        0x3c => op("EXTCODECOPY", 4, 0, 20),
+        0x3d => op("RETURNDATASIZE", 0, 1, BASE).since(Byzantium),
+        0x3e => op("RETURNDATACOPY", 3, 0, VERYLOW).since(Byzantium),
+        0x3f => op("EXTCODEHASH", 1, 1, 400).since(Constantinople),
+        0x40 => op("BLOCKHASH", 1, 1, 20),
+        0x41 => op("COINBASE", 0, 1, BASE),
+        0x42 => op("TIMESTAMP", 0, 1, BASE),
+        0x43 => op("NUMBER", 0, 1, BASE),
+        0x44 => op("PREVRANDAO", 0, 1, BASE),
+        0x45 => op("GASLIMIT", 0, 1, BASE),
+        0x46 => op("CHAINID", 0, 1, BASE).since(Istanbul),
+        0x47 => op("SELFBALANCE", 0, 1, LOW).since(Istanbul),
+        0x48 => op("BASEFEE", 0, 1, BASE).since(London),
+        0x49 => op("BLOBHASH", 1, 1, VERYLOW).since(Cancun),
+        0x4a => op("BLOBBASEFEE", 0, 1, BASE).since(Cancun),
+        0x50 => op("POP", 1, 0, BASE),
+        0x51 => op("MLOAD", 1, 1, VERYLOW),
+        0x52 => op("MSTORE", 2, 0, VERYLOW),
+        0x53 => op("MSTORE8", 2, 0, VERYLOW),
+        0x54 => op("SLOAD", 1, 1, 50),
+        0x55 => op("SSTORE", 2, 0, ZERO),
+        0x56 => op("JUMP", 1, 0, MID),
+        0x57 => op("JUMPI", 2, 0, HIGH),
+        0x58 => op("PC", 0, 1, BASE),
+        0x59 => op("MSIZE", 0, 1, BASE),
+        0x5a => op("GAS", 0, 1, BASE),
+        0x5b => op("JUMPDEST", 0, 0, JUMPDEST),
+        0x5c => op("TLOAD", 1, 1, 100).since(Cancun),
+        0x5d => op("TSTORE", 2, 0, 100).since(Cancun),
+        0x5e => op("MCOPY", 3, 0, VERYLOW).since(Cancun),
+        0x5f => op("PUSH0", 0, 1, BASE).since(Shanghai),
+        0x60 => op("PUSH1", 0, 1, VERYLOW),
+        0x61 => op("PUSH2", 0, 1, VERYLOW),
+        0x62 => op("PUSH3", 0, 1, VERYLOW),
+        0x63 => op("PUSH4", 0, 1, VERYLOW),
+        0x64 => op("PUSH5", 0, 1, VERYLOW),
+        0x65 => op("PUSH6", 0, 1, VERYLOW),
+        0x66 => op("PUSH7", 0, 1, VERYLOW),
+        0x67 => op("PUSH8", 0, 1, VERYLOW),
+        0x68 => op("PUSH9", 0, 1, VERYLOW),
+        0x69 => op("PUSH10", 0, 1, VERYLOW),
+        0x6a => op("PUSH11", 0, 1, VERYLOW),
+        0x6b => op("PUSH12", 0, 1, VERYLOW),
+        0x6c => op("PUSH13", 0, 1, VERYLOW),
+        0x6d => op("PUSH14", 0, 1, VERYLOW),
+        0x6e => op("PUSH15", 0, 1, VERYLOW),
+        0x6f => op("PUSH16", 0, 1, VERYLOW),
+        0x70 => op("PUSH17", 0, 1, VERYLOW),
+        0x71 => op("PUSH18", 0, 1, VERYLOW),
+        0x72 => op("PUSH19", 0, 1, VERYLOW),
+        0x73 => op("PUSH20", 0, 1, VERYLOW),
+        0x74 => op("PUSH21", 0, 1, VERYLOW),
+        0x75 => op("PUSH22", 0, 1, VERYLOW),
+        0x76 => op("PUSH23", 0, 1, VERYLOW),
+        0x77 => op("PUSH24", 0, 1, VERYLOW),
+        0x78 => op("PUSH25", 0, 1, VERYLOW),
+        0x79 => op("PUSH26", 0, 1, VERYLOW),
+        0x7a => op("PUSH27", 0, 1, VERYLOW),
+        0x7b => op("PUSH28", 0, 1, VERYLOW),
+        0x7c => op("PUSH29", 0, 1, VERYLOW),
+        0x7d => op("PUSH30", 0, 1, VERYLOW),
+        0x7e => op("PUSH31", 0, 1, VERYLOW),
+        0x7f => op("PUSH32", 0, 1, VERYLOW),
+        0x80 => op("DUP1", 1, 2, VERYLOW),
+        0x81 => op("DUP2", 2, 3, VERYLOW),
+        0x82 => op("DUP3", 3, 4, VERYLOW),
+        0x83 => op("DUP4", 4, 5, VERYLOW),
+        0x84 => op("DUP5", 5, 6, VERYLOW),
+        0x85 => op("DUP6", 6, 7, VERYLOW),
+        0x86 => op("DUP7", 7, 8, VERYLOW),
+        0x87 => op("DUP8", 8, 9, VERYLOW),
+        0x88 => op("DUP9", 9, 10, VERYLOW),
+        0x89 => op("DUP10", 10, 11, VERYLOW),
+        0x8a => op("DUP11", 11, 12, VERYLOW),
+        0x8b => op("DUP12", 12, 13, VERYLOW),
+        0x8c => op("DUP13", 13, 14, VERYLOW),
+        0x8d => op("DUP14", 14, 15, VERYLOW),
+        0x8e => op("DUP15", 15, 16, VERYLOW),
+        0x8f => op("DUP16", 16, 17, VERYLOW),
+        0x90 => op("SWAP1", 2, 2, VERYLOW),
+        0x91 => op("SWAP2", 3, 3, VERYLOW),
+        0x92 => op("SWAP3", 4, 4, VERYLOW),
+        0x93 => op("SWAP4", 5, 5, VERYLOW),
+        0x94 => op("SWAP5", 6, 6, VERYLOW),
+        0x95 => op("SWAP6", 7, 7, VERYLOW),
+        0x96 => op("SWAP7", 8, 8, VERYLOW),
+        0x97 => op("SWAP8", 9, 9, VERYLOW),
+        0x98 => op("SWAP9", 10, 10, VERYLOW),
+        0x99 => op("SWAP10", 11, 11, VERYLOW),
+        0x9a => op("SWAP11", 12, 12, VERYLOW),
+        0x9b => op("SWAP12", 13, 13, VERYLOW),
+        0x9c => op("SWAP13", 14, 14, VERYLOW),
+        0x9d => op("SWAP14", 15, 15, VERYLOW),
+        0x9e => op("SWAP15", 16, 16, VERYLOW),
+        0x9f => op("SWAP16", 17, 17, VERYLOW),
+        0xa0 => op("LOG0", 2, 0, 375),
+        0xa1 => op("LOG1", 3, 0, 750),
+        0xa2 => op("LOG2", 4, 0, 1125),
+        0xa3 => op("LOG3", 5, 0, 1500),
+        0xa4 => op("LOG4", 6, 0, 1875),
+        0xf0 => op("CREATE", 3, 1, 32000),
+        0xf1 => op("CALL", 7, 1, 40),
+        0xf2 => op("CALLCODE", 7, 1, 40),
+        0xf3 => op("RETURN", 2, 0, ZERO),
+        0xf4 => op("DELEGATECALL", 6, 1, 40).since(Homestead),
+        0xf5 => op("CREATE2", 4, 1, 32000).since(Constantinople),
+        0xfa => op("STATICCALL", 6, 1, 40).since(Byzantium),
+        0xfd => op("REVERT", 2, 0, ZERO).since(Byzantium),
+        0xfe => op("INVALID", 0, 0, ZERO),
+        0xff => op("SELFDESTRUCT", 1, 0, ZERO),
+        _ => return None,
+    };
+
+    Some(info)
+}
+
+/// every byte mapped to its opcode, `None` where the byte is unassigned
+pub static OPCODE_TABLE: [Option<OpcodeInfo>; 256] = {
+    let mut table = [None; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = lookup(i as u8);
+        i += 1;
+    }
+    table
+};
+
+/// everything known about `opcode`, `None` if it's unassigned
+pub fn opcode_info(opcode: u8) -> Option<OpcodeInfo> {
+    OPCODE_TABLE[opcode as usize]
+}
+
+/// mnemonic of `opcode`, `None` if it's unassigned
+pub fn opcode_name(opcode: u8) -> Option<&'static str> {
+    OPCODE_TABLE[opcode as usize].map(|info| info.name)
+}
+
+/// `code` as assembly, one instruction per line
+pub fn disassemble(code: &[u8]) -> String {
+    let mut lines = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        pc += 1;
+
+        let line = match OPCODE_TABLE[opcode as usize] {
+            Some(info) if (PUSH1..=PUSH32).contains(&opcode) => {
+                // the data can be cut short by the end of the code
+                let size = (opcode - PUSH1 + 1) as usize;
+                let data = &code[pc..(pc + size).min(code.len())];
+                pc += size;
+                format!("{} 0x{}", info.name, hex::encode(data))
+            }
+            Some(info) => info.name.to_string(),
+            None => format!("0x{:02x}", opcode),
+        };
+        lines.push(line);
+    }
+
+    lines.join("\n")
+}
+
+/// whether `opcode` is assigned in `spec`
+pub fn is_enabled(opcode: u8, spec: SpecId) -> bool {
+    match OPCODE_TABLE[opcode as usize] {
+        Some(info) => spec.is_enabled_in(info.since),
+        None => false,
+    }
+}
+
+/// everything checked before running an opcode under one spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeRules {
+    pub inputs: u8,
+    pub outputs: u8,
+    /// fixed cost with the spec's repricing applied
+    pub gas: u64,
+}
+
+/// rules for every opcode under a spec, `None` where it isn't available
+pub type SpecRules = [Option<OpcodeRules>; 256];
+
+const SPECS: usize = Prague as usize + 1;
+
+/// the rules for `spec`, worked out the first time the spec is used
+pub fn spec_rules(spec: SpecId) -> &'static SpecRules {
+    static RULES: [OnceLock<SpecRules>; SPECS] = [const { OnceLock::new() }; SPECS];
+
+    RULES[spec as usize].get_or_init(|| {
+        let mut rules = [None; 256];
+        for opcode in 0..=255u8 {
+            let info = OPCODE_TABLE[opcode as usize].filter(|_| is_enabled(opcode, spec));
+            rules[opcode as usize] = info.map(|info| OpcodeRules {
+                inputs: info.inputs,
+                outputs: info.outputs,
+                gas: gas::static_cost(opcode, spec),
+            });
+        }
+        rules
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disassemble, is_enabled, opcode_info, opcode_name, spec_rules, OPCODE_TABLE};
+    use crate::{evm, HaltReason, SpecId};
+
+    #[test]
+    fn table() {
+        assert_eq!(opcode_name(0x01), Some("ADD"));
+        assert_eq!(opcode_name(0x7f), Some("PUSH32"));
+        assert_eq!(opcode_name(0xff), Some("SELFDESTRUCT"));
+        assert_eq!(opcode_name(0xef), None);
+        assert_eq!(OPCODE_TABLE.iter().filter(|op| op.is_some()).count(), 149);
+    }
+
+    #[test]
+    fn stack_effects() {
+        let effect = |opcode| {
+            let info = opcode_info(opcode).unwrap();
+            (info.inputs, info.outputs)
+        };
+        assert_eq!(effect(0x01), (2, 1));
+        assert_eq!(effect(0x8f), (16, 17));
+        assert_eq!(effect(0x9f), (17, 17));
+        assert_eq!(effect(0xa4), (6, 0));
+        assert_eq!(effect(0xf1), (7, 1));
+    }
+
+    #[test]
+    fn rules() {
+        let gas = |opcode: u8, spec| spec_rules(spec)[opcode as usize].map(|rules| rules.gas);
+
+        // BALANCE is repriced, ADD never is
+        assert_eq!(gas(0x31, SpecId::Frontier), Some(20));
+        assert_eq!(gas(0x31, SpecId::Istanbul), Some(700));
+        assert_eq!(gas(0x31, SpecId::Berlin), Some(100));
+        assert_eq!(gas(0x01, SpecId::Frontier), Some(3));
+        assert_eq!(gas(0x01, SpecId::Cancun), Some(3));
+
+        // PUSH0 only exists from Shanghai
+        assert_eq!(gas(0x5f, SpecId::Paris), None);
+        assert_eq!(gas(0x5f, SpecId::Shanghai), Some(2));
+        assert_eq!(gas(0xef, SpecId::Prague), None);
+
+        for spec in [SpecId::Frontier, SpecId::Berlin, SpecId::Prague] {
+            for opcode in 0..=255u8 {
+                let rules = spec_rules(spec)[opcode as usize];
+                assert_eq!(rules.is_some(), is_enabled(opcode, spec), "{:#x}", opcode);
+                if let (Some(rules), Some(info)) = (rules, opcode_info(opcode)) {
+                    assert_eq!((rules.inputs, rules.outputs), (info.inputs, info.outputs));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn disassembly() {
+        assert_eq!(
+            disassemble(&hex::decode("602a600055ef61ff").unwrap()),
+            "PUSH1 0x2a\nPUSH1 0x00\nSSTORE\n0xef\nPUSH2 0xff"
+        );
+    }
+
+    #[test]
+    fn undefined_opcodes_halt() {
+        for opcode in 0..=255u8 {
+            if is_enabled(opcode, SpecId::default()) {
+                continue;
+            }
+
+            // PUSH1 1\n<undefined>\nPUSH1 2
+            let result = evm([0x60, 0x01, opcode, 0x60, 0x02]);
+            assert_eq!(result.reason, HaltReason::InvalidOpcode, "{:#x}", opcode);
+            assert_eq!(result.pc, 2);
+            assert_eq!(result.opcode, opcode);
+            assert_eq!(result.stack, vec![1.into()]);
+        }
+    }
+}
@@ -0,0 +1,776 @@
+use crate::gas::words;
+use crate::hash::sha3;
+use crate::spec::SpecId::{self, *};
+use crate::utils::padded_slice;
+use crate::{Address, HaltReason};
+use primitive_types::{H256, U256};
+use std::collections::HashMap;
+
+// fixed and per word costs
+const ECRECOVER: u64 = 3000;
+const SHA256_BASE: u64 = 60;
+const SHA256_WORD: u64 = 12;
+const RIPEMD160_BASE: u64 = 600;
+const RIPEMD160_WORD: u64 = 120;
+const IDENTITY_BASE: u64 = 15;
+const IDENTITY_WORD: u64 = 3;
+const POINT_EVALUATION: u64 = 50000;
+
+/// what a precompile hands back when it succeeds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrecompileOutput {
+    pub gas_used: u64,
+    pub output: Vec<u8>,
+}
+
+/// a failed precompile burns all the gas it was given
+pub type PrecompileResult = Result<PrecompileOutput, HaltReason>;
+
+/// native code run in place of a contract, given the call data, the gas limit and the fork
+pub type Precompile = fn(&[u8], u64, SpecId) -> PrecompileResult;
+
+// the standard precompiles, with the fork that introduced each one
+const STANDARD: [(u64, SpecId, Precompile); 10] = [
+    (0x01, Frontier, ecrecover),
+    (0x02, Frontier, sha256),
+    (0x03, Frontier, ripemd160),
+    (0x04, Frontier, identity),
+    (0x05, Byzantium, modexp),
+    (0x06, Byzantium, bn254_add),
+    (0x07, Byzantium, bn254_mul),
+    (0x08, Byzantium, bn254_pairing),
+    (0x09, Istanbul, blake2f),
+    (0x0a, Cancun, point_evaluation),
+];
+
+/// the contracts implemented natively, the standard ones plus any registered by the user
+#[derive(Debug, Clone, Default)]
+pub struct Precompiles {
+    custom: HashMap<Address, Precompile>,
+}
+
+impl Precompiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// run `precompile` for calls to `address`, replacing any standard one there
+    pub fn register(&mut self, address: Address, precompile: Precompile) {
+        self.custom.insert(address, precompile);
+    }
+
+    /// the precompile at `address` under `spec`, if there is one
+    pub fn get(&self, address: Address, spec: SpecId) -> Option<Precompile> {
+        match self.custom.get(&address) {
+            Some(precompile) => Some(*precompile),
+            None => standard(spec)
+                .find(|(standard, _)| *standard == address)
+                .map(|(_, precompile)| precompile),
+        }
+    }
+
+    /// every address with a precompile under `spec`
+    pub fn addresses(&self, spec: SpecId) -> impl Iterator<Item = Address> + '_ {
+        standard(spec)
+            .map(|(address, _)| address)
+            .filter(|address| !self.custom.contains_key(address))
+            .chain(self.custom.keys().copied())
+    }
+}
+
+fn standard(spec: SpecId) -> impl Iterator<Item = (Address, Precompile)> {
+    STANDARD
+        .into_iter()
+        .filter(move |(_, since, _)| spec.is_enabled_in(*since))
+        .map(|(address, _, precompile)| (Address::from_low_u64_be(address), precompile))
+}
+
+// fail before doing any work if `cost` is more than the call was given
+fn charge(cost: u64, gas_limit: u64) -> Result<(), HaltReason> {
+    if cost > gas_limit {
+        return Err(HaltReason::OutOfGas);
+    }
+
+    Ok(())
+}
+
+fn success(gas_used: u64, output: Vec<u8>) -> PrecompileResult {
+    Ok(PrecompileOutput { gas_used, output })
+}
+
+// `input` cut or padded with zeros to `size` bytes
+fn padded(input: &[u8], size: usize) -> Vec<u8> {
+    padded_slice(input, U256::zero(), size)
+}
+
+// recover the address that signed a message hash, or nothing if the signature is invalid
+fn ecrecover(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    charge(ECRECOVER, gas_limit)?;
+
+    let input = padded(input, 128);
+    let output = recover(&input)
+        .map(|address| H256::from(address).as_bytes().to_vec())
+        .unwrap_or_default();
+    success(ECRECOVER, output)
+}
+
+fn recover(input: &[u8]) -> Option<Address> {
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+    // v takes up a whole word, and has to be 27 or 28
+    let v = U256::from_big_endian(&input[32..64]);
+    if v != 27.into() && v != 28.into() {
+        return None;
+    }
+
+    // signatures with a high s are fine here, but not to k256
+    let mut signature = Signature::from_slice(&input[64..128]).ok()?;
+    let mut parity = input[63] - 27;
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        parity ^= 1;
+    }
+
+    let id = RecoveryId::from_byte(parity)?;
+    let key = VerifyingKey::recover_from_prehash(&input[..32], &signature, id).ok()?;
+    let mut hash = [0; 32];
+    sha3(&key.to_encoded_point(false).as_bytes()[1..], &mut hash);
+    Some(Address::from_slice(&hash[12..]))
+}
+
+fn sha256(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    use sha2::{Digest, Sha256};
+
+    let cost = SHA256_BASE + SHA256_WORD * words(input.len() as u64);
+    charge(cost, gas_limit)?;
+
+    success(cost, Sha256::digest(input).to_vec())
+}
+
+fn ripemd160(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    use ripemd::{Digest, Ripemd160};
+
+    let cost = RIPEMD160_BASE + RIPEMD160_WORD * words(input.len() as u64);
+    charge(cost, gas_limit)?;
+
+    // the 20 byte hash is padded out to a word
+    let mut output = vec![0; 12];
+    output.extend_from_slice(&Ripemd160::digest(input));
+    success(cost, output)
+}
+
+fn identity(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    let cost = IDENTITY_BASE + IDENTITY_WORD * words(input.len() as u64);
+    charge(cost, gas_limit)?;
+    success(cost, input.to_vec())
+}
+
+// `base ** exponent % modulus` for arbitrarily large numbers (EIP-198)
+fn modexp(input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult {
+    let header = padded(input, 96);
+    let base_len = length(&header[..32]);
+    let exp_len = length(&header[32..64]);
+    let mod_len = length(&header[64..96]);
+
+    // only the top word of the exponent goes into the price
+    let data = input.get(96..).unwrap_or_default();
+    let exp_head = padded_slice(data, base_len.into(), exp_len.min(32) as usize);
+    let cost = modexp_cost(base_len, exp_len, mod_len, &exp_head, spec);
+    charge(cost, gas_limit)?;
+    if mod_len == 0 {
+        return success(cost, Vec::new());
+    }
+
+    let base = padded_slice(data, U256::zero(), base_len as usize);
+    let exponent = padded_slice(data, base_len.into(), exp_len as usize);
+    let modulus = padded_slice(data, (base_len + exp_len).into(), mod_len as usize);
+    let result = aurora_engine_modexp::modexp(&base, &exponent, &modulus);
+
+    // the result takes up as many bytes as the modulus
+    let mut output = vec![0; mod_len as usize - result.len()];
+    output.extend_from_slice(&result);
+    success(cost, output)
+}
+
+// a length from the `MODEXP` header, saturated so an absurd one prices itself out
+fn length(word: &[u8]) -> u64 {
+    let length = U256::from_big_endian(word);
+    if length > u32::MAX.into() {
+        u32::MAX as u64
+    } else {
+        length.as_u64()
+    }
+}
+
+fn modexp_cost(base_len: u64, exp_len: u64, mod_len: u64, exp_head: &[u8], spec: SpecId) -> u64 {
+    // roughly how many multiplications square and multiply takes
+    let head = U256::from_big_endian(exp_head);
+    let head_bits = (head.bits() as u64).saturating_sub(1);
+    let iterations = if exp_len <= 32 {
+        head_bits
+    } else {
+        8 * (exp_len - 32) + head_bits
+    }
+    .max(1) as u128;
+
+    let x = base_len.max(mod_len) as u128;
+    let cost = if spec.is_enabled_in(Berlin) {
+        // cheaper pricing based on words rather than bytes (EIP-2565)
+        let words = x.div_ceil(8);
+        (words * words * iterations / 3).max(200)
+    } else {
+        let complexity = if x <= 64 {
+            x * x
+        } else if x <= 1024 {
+            x * x / 4 + 96 * x - 3072
+        } else {
+            x * x / 16 + 480 * x - 199680
+        };
+        complexity * iterations / 20
+    };
+    cost.try_into().unwrap_or(u64::MAX)
+}
+
+// a point on the alt_bn128 curve, encoded as two big endian coordinates
+fn g1_point(input: &[u8]) -> Result<substrate_bn::G1, HaltReason> {
+    use substrate_bn::{AffineG1, Fq, Group, G1};
+
+    let x = Fq::from_slice(&input[..32]).map_err(|_| HaltReason::PrecompileError)?;
+    let y = Fq::from_slice(&input[32..64]).map_err(|_| HaltReason::PrecompileError)?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1::zero());
+    }
+
+    AffineG1::new(x, y)
+        .map(G1::from)
+        .map_err(|_| HaltReason::PrecompileError)
+}
+
+// a point on the twisted curve, each coordinate with its imaginary part first (EIP-197)
+fn g2_point(input: &[u8]) -> Result<substrate_bn::G2, HaltReason> {
+    use substrate_bn::{AffineG2, Fq, Fq2, Group, G2};
+
+    let mut parts = [Fq::zero(); 4];
+    for (part, bytes) in parts.iter_mut().zip(input.chunks(32)) {
+        *part = Fq::from_slice(bytes).map_err(|_| HaltReason::PrecompileError)?;
+    }
+    let x = Fq2::new(parts[1], parts[0]);
+    let y = Fq2::new(parts[3], parts[2]);
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2::zero());
+    }
+
+    AffineG2::new(x, y)
+        .map(G2::from)
+        .map_err(|_| HaltReason::PrecompileError)
+}
+
+fn encode_g1(point: substrate_bn::G1) -> Vec<u8> {
+    let mut output = vec![0; 64];
+    if let Some(point) = substrate_bn::AffineG1::from_jacobian(point) {
+        point.x().to_big_endian(&mut output[..32]).unwrap();
+        point.y().to_big_endian(&mut output[32..]).unwrap();
+    }
+
+    output
+}
+
+// EIP-196, repriced by EIP-1108
+fn bn254_add(input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult {
+    let cost = if spec.is_enabled_in(Istanbul) {
+        150
+    } else {
+        500
+    };
+    charge(cost, gas_limit)?;
+
+    let input = padded(input, 128);
+    let sum = g1_point(&input[..64])? + g1_point(&input[64..])?;
+    success(cost, encode_g1(sum))
+}
+
+fn bn254_mul(input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult {
+    let cost = if spec.is_enabled_in(Istanbul) {
+        6000
+    } else {
+        40000
+    };
+    charge(cost, gas_limit)?;
+
+    let input = padded(input, 96);
+    let point = g1_point(&input[..64])?;
+    let scalar =
+        substrate_bn::Fr::from_slice(&input[64..]).map_err(|_| HaltReason::PrecompileError)?;
+    success(cost, encode_g1(point * scalar))
+}
+
+// whether the product of the pairings of each (G1, G2) pair is one (EIP-197)
+fn bn254_pairing(input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult {
+    let pairs = input.len() as u64 / 192;
+    let cost = if spec.is_enabled_in(Istanbul) {
+        45000 + 34000 * pairs
+    } else {
+        100000 + 80000 * pairs
+    };
+    charge(cost, gas_limit)?;
+    if !input.len().is_multiple_of(192) {
+        return Err(HaltReason::PrecompileError);
+    }
+
+    let mut points = Vec::new();
+    for pair in input.chunks(192) {
+        points.push((g1_point(&pair[..64])?, g2_point(&pair[64..])?));
+    }
+    let holds = substrate_bn::pairing_batch(&points) == substrate_bn::Gt::one();
+
+    let mut output = vec![0; 32];
+    output[31] = holds as u8;
+    success(cost, output)
+}
+
+// the BLAKE2b compression function, with the number of rounds chosen by the caller (EIP-152)
+fn blake2f(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    if input.len() != 213 {
+        return Err(HaltReason::PrecompileError);
+    }
+
+    let rounds = u32::from_be_bytes(input[..4].try_into().unwrap());
+    charge(rounds as u64, gas_limit)?;
+
+    let word = |i: usize| u64::from_le_bytes(input[i..i + 8].try_into().unwrap());
+    let mut h: [u64; 8] = std::array::from_fn(|i| word(4 + 8 * i));
+    let m: [u64; 16] = std::array::from_fn(|i| word(68 + 8 * i));
+    let t = [word(196), word(204)];
+    let f = match input[212] {
+        0 => false,
+        1 => true,
+        _ => return Err(HaltReason::PrecompileError),
+    };
+
+    blake2::compress(rounds, &mut h, &m, t, f);
+    let output = h.iter().flat_map(|word| word.to_le_bytes()).collect();
+    success(rounds as u64, output)
+}
+
+mod blake2 {
+    const IV: [u64; 8] = [
+        0x6a09e667f3bcc908,
+        0xbb67ae8584caa73b,
+        0x3c6ef372fe94f82b,
+        0xa54ff53a5f1d36f1,
+        0x510e527fade682d1,
+        0x9b05688c2b3e6c1f,
+        0x1f83d9abfb41bd6b,
+        0x5be0cd19137e2179,
+    ];
+
+    const SIGMA: [[usize; 16]; 10] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+        [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+        [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+        [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+        [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+        [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+        [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+        [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+        [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+        [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+    ];
+
+    // the mixing function G
+    fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+        v[d] = (v[d] ^ v[a]).rotate_right(32);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(24);
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+        v[d] = (v[d] ^ v[a]).rotate_right(16);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(63);
+    }
+
+    /// the compression function F from RFC 7693
+    pub fn compress(rounds: u32, h: &mut [u64; 8], m: &[u64; 16], t: [u64; 2], f: bool) {
+        let mut v = [0; 16];
+        v[..8].copy_from_slice(h);
+        v[8..].copy_from_slice(&IV);
+        v[12] ^= t[0];
+        v[13] ^= t[1];
+        if f {
+            v[14] = !v[14];
+        }
+
+        for round in 0..rounds as usize {
+            let s = &SIGMA[round % 10];
+            mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+            mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+            mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+            mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+            mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+            mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+            mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+            mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+        }
+
+        for i in 0..8 {
+            h[i] ^= v[i] ^ v[i + 8];
+        }
+    }
+}
+
+// check that a blob committed to by a versioned hash evaluates to `y` at `z` (EIP-4844)
+fn point_evaluation(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    use c_kzg::{Bytes32, Bytes48};
+    use sha2::{Digest, Sha256};
+
+    charge(POINT_EVALUATION, gas_limit)?;
+    if input.len() != 192 {
+        return Err(HaltReason::PrecompileError);
+    }
+
+    // the versioned hash is the hash of the commitment with a version byte in front
+    let commitment = &input[96..144];
+    let mut versioned_hash = Sha256::digest(commitment);
+    versioned_hash[0] = 0x01;
+    if versioned_hash[..] != input[..32] {
+        return Err(HaltReason::PrecompileError);
+    }
+
+    let settings = c_kzg::ethereum_kzg_settings(0);
+    let valid = settings.verify_kzg_proof(
+        &Bytes48::from_bytes(commitment).map_err(|_| HaltReason::PrecompileError)?,
+        &Bytes32::from_bytes(&input[32..64]).map_err(|_| HaltReason::PrecompileError)?,
+        &Bytes32::from_bytes(&input[64..96]).map_err(|_| HaltReason::PrecompileError)?,
+        &Bytes48::from_bytes(&input[144..]).map_err(|_| HaltReason::PrecompileError)?,
+    );
+    if !matches!(valid, Ok(true)) {
+        return Err(HaltReason::PrecompileError);
+    }
+
+    // the number of field elements in a blob and the BLS modulus
+    let output = hex::decode(
+        "0000000000000000000000000000000000000000000000000000000000001000\
+         73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001",
+    )
+    .unwrap();
+    success(POINT_EVALUATION, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrecompileOutput, PrecompileResult, Precompiles};
+    use crate::{execute, Address, Env, HaltReason, InMemoryDb, SpecId};
+    use primitive_types::U256;
+
+    const G1: &str = "0000000000000000000000000000000000000000000000000000000000000001\
+                      0000000000000000000000000000000000000000000000000000000000000002";
+    const G1_NEG: &str = "0000000000000000000000000000000000000000000000000000000000000001\
+                          30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd45";
+    const G1_DOUBLE: &str = "030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3\
+                             15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4";
+    const G2: &str = "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2\
+                      1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed\
+                      090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b\
+                      12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa";
+
+    // call the standard precompile at `address` with plenty of gas
+    fn run(address: u64, input: &str, spec: SpecId) -> PrecompileResult {
+        let precompile = Precompiles::new()
+            .get(Address::from_low_u64_be(address), spec)
+            .unwrap();
+        precompile(&hex::decode(input).unwrap(), 1_000_000, spec)
+    }
+
+    fn output(gas_used: u64, output: &str) -> PrecompileResult {
+        Ok(PrecompileOutput {
+            gas_used,
+            output: hex::decode(output).unwrap(),
+        })
+    }
+
+    #[test]
+    fn availability() {
+        let precompiles = Precompiles::new();
+        let count = |spec| precompiles.addresses(spec).count();
+        assert_eq!(count(SpecId::Frontier), 4);
+        assert_eq!(count(SpecId::Byzantium), 8);
+        assert_eq!(count(SpecId::Istanbul), 9);
+        assert_eq!(count(SpecId::Cancun), 10);
+        assert!(precompiles
+            .get(Address::from_low_u64_be(0x0b), SpecId::Prague)
+            .is_none());
+    }
+
+    #[test]
+    fn ecrecover() {
+        let input = "38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e\
+                     000000000000000000000000000000000000000000000000000000000000001b\
+                     38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e\
+                     789d1dd423d25f0772d2748d60f7e4b81bb14d086eba8e8e8efb6dcff8a4ae02";
+        assert_eq!(
+            run(0x01, input, SpecId::Cancun),
+            output(
+                3000,
+                "000000000000000000000000ceaccac640adf55b2028469bd36ba501f28b699d"
+            )
+        );
+
+        // a v other than 27 or 28 recovers nothing, but still succeeds
+        let input = input.replace("001b", "001d");
+        assert_eq!(run(0x01, &input, SpecId::Cancun), output(3000, ""));
+    }
+
+    #[test]
+    fn hashes() {
+        assert_eq!(
+            run(0x02, "", SpecId::Cancun),
+            output(
+                60,
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+            )
+        );
+        assert_eq!(
+            run(0x03, "", SpecId::Cancun),
+            output(
+                600,
+                "0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31"
+            )
+        );
+        assert_eq!(
+            run(0x04, "c0ffee", SpecId::Cancun),
+            output(15 + 3, "c0ffee")
+        );
+    }
+
+    #[test]
+    fn modexp() {
+        // 3 ** (p - 1) % p is 1 for the prime p, from EIP-198
+        let input = "0000000000000000000000000000000000000000000000000000000000000001\
+                     0000000000000000000000000000000000000000000000000000000000000020\
+                     0000000000000000000000000000000000000000000000000000000000000020\
+                     03\
+                     fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e\
+                     fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f";
+        let one = "0000000000000000000000000000000000000000000000000000000000000001";
+        assert_eq!(run(0x05, input, SpecId::Byzantium), output(13056, one));
+
+        // priced by 8 byte words since EIP-2565, 4 * 4 * 255 / 3
+        assert_eq!(run(0x05, input, SpecId::Berlin), output(1360, one));
+
+        // a zero modulus gives zero, padded to its length
+        let input = "0000000000000000000000000000000000000000000000000000000000000001\
+                     0000000000000000000000000000000000000000000000000000000000000001\
+                     0000000000000000000000000000000000000000000000000000000000000002\
+                     03050000";
+        assert_eq!(run(0x05, input, SpecId::Berlin), output(200, "0000"));
+    }
+
+    #[test]
+    fn bn254_add() {
+        let input = format!("{}{}", G1, G1);
+        assert_eq!(run(0x06, &input, SpecId::Istanbul), output(150, G1_DOUBLE));
+        assert_eq!(run(0x06, &input, SpecId::Byzantium), output(500, G1_DOUBLE));
+
+        // a point and its negation add up to the point at infinity
+        let input = format!("{}{}", G1, G1_NEG);
+        assert_eq!(
+            run(0x06, &input, SpecId::Cancun),
+            output(150, &"00".repeat(64))
+        );
+
+        // (1, 1) isn't on the curve
+        let input = format!("{}{}", G1, G1.replace("0002", "0001"));
+        assert_eq!(
+            run(0x06, &input, SpecId::Cancun),
+            Err(HaltReason::PrecompileError)
+        );
+    }
+
+    #[test]
+    fn bn254_mul() {
+        let input = format!("{}{:064x}", G1, 2);
+        assert_eq!(run(0x07, &input, SpecId::Istanbul), output(6000, G1_DOUBLE));
+        assert_eq!(
+            run(0x07, &input, SpecId::Byzantium),
+            output(40000, G1_DOUBLE)
+        );
+    }
+
+    #[test]
+    fn bn254_pairing() {
+        let one = "0000000000000000000000000000000000000000000000000000000000000001";
+        let zero = "0000000000000000000000000000000000000000000000000000000000000000";
+
+        // no pairs at all trivially holds
+        assert_eq!(run(0x08, "", SpecId::Istanbul), output(45000, one));
+
+        // e(G1, G2) * e(-G1, G2) = 1
+        let input = format!("{}{}{}{}", G1, G2, G1_NEG, G2);
+        assert_eq!(
+            run(0x08, &input, SpecId::Istanbul),
+            output(45000 + 2 * 34000, one)
+        );
+        assert_eq!(
+            run(0x08, &input, SpecId::Byzantium),
+            output(100000 + 2 * 80000, one)
+        );
+
+        let input = format!("{}{}{}{}", G1, G2, G1, G2);
+        assert_eq!(
+            run(0x08, &input, SpecId::Istanbul),
+            output(45000 + 2 * 34000, zero)
+        );
+
+        // the input has to be made up of whole pairs
+        assert_eq!(
+            run(0x08, G1, SpecId::Istanbul),
+            Err(HaltReason::PrecompileError)
+        );
+    }
+
+    #[test]
+    fn blake2f() {
+        // test vector 5 from EIP-152, a full hash of "abc"
+        let input = "0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b\
+                     61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000\
+                     00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000\
+                     0300000000000000000000000000000001";
+        assert_eq!(
+            run(0x09, input, SpecId::Istanbul),
+            output(
+                12,
+                "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+                 7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+            )
+        );
+
+        // the final block flag has to be 0 or 1
+        let input = format!("{}02", &input[..input.len() - 2]);
+        assert_eq!(
+            run(0x09, &input, SpecId::Istanbul),
+            Err(HaltReason::PrecompileError)
+        );
+        assert_eq!(
+            run(0x09, &input[2..], SpecId::Istanbul),
+            Err(HaltReason::PrecompileError)
+        );
+    }
+
+    #[test]
+    fn point_evaluation() {
+        // the zero polynomial, committed to by the point at infinity, is zero everywhere
+        let infinity = format!("c0{}", "00".repeat(47));
+        let input = format!(
+            "010657f37554c781402a22917dee2f75def7ab966d7b770905398eba3c444014{:064x}{:064x}{}{}",
+            5, 0, infinity, infinity
+        );
+        assert_eq!(
+            run(0x0a, &input, SpecId::Cancun),
+            output(
+                50000,
+                "0000000000000000000000000000000000000000000000000000000000001000\
+                 73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001"
+            )
+        );
+
+        // but not one
+        let input = format!(
+            "010657f37554c781402a22917dee2f75def7ab966d7b770905398eba3c444014{:064x}{:064x}{}{}",
+            5, 1, infinity, infinity
+        );
+        assert_eq!(
+            run(0x0a, &input, SpecId::Cancun),
+            Err(HaltReason::PrecompileError)
+        );
+    }
+
+    #[test]
+    fn out_of_gas() {
+        let precompile = Precompiles::new()
+            .get(Address::from_low_u64_be(0x01), SpecId::Cancun)
+            .unwrap();
+        assert_eq!(
+            precompile(&[], 2999, SpecId::Cancun),
+            Err(HaltReason::OutOfGas)
+        );
+    }
+
+    #[test]
+    fn call() {
+        let env = Env {
+            gas_limit: 100_000,
+            ..Env::default()
+        };
+
+        // PUSH1 0x2a\nPUSH1 0\nMSTORE
+        // CALL 0x04 with 32 bytes of input at 0 and output at 32\nPUSH1 32\nMLOAD
+        let bin = "602a600052602060206020600060006004610ffff1602051";
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut InMemoryDb::new());
+        assert_eq!(result.stack, vec![1.into(), 0x2a.into()]);
+
+        // precompiles start warm, and the identity copies one word
+        assert_eq!(
+            result.gas_used,
+            3 * 3 + 3 + 7 * 3 + 100 + 3 + 15 + 3 + 3 + 3
+        );
+
+        // invalid input fails the call and burns the gas it was given
+        // CALL 0x09 with no input
+        let bin = "600060006000600060006009610ffff1";
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut InMemoryDb::new());
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into()]);
+        assert_eq!(result.gas_used, 7 * 3 + 100 + 0x0fff);
+    }
+
+    #[test]
+    fn custom() {
+        // reverses its input for a flat 10 gas
+        fn reverse(input: &[u8], _gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+            Ok(PrecompileOutput {
+                gas_used: 10,
+                output: input.iter().rev().copied().collect(),
+            })
+        }
+
+        let mut env = Env {
+            gas_limit: 100_000,
+            ..Env::default()
+        };
+        env.precompiles
+            .register(Address::from_low_u64_be(0xbeef), reverse);
+
+        // PUSH2 0x0102\nPUSH1 0\nMSTORE
+        // CALL 0xbeef with 32 bytes of input at 0 and output at 32\nPUSH1 32\nMLOAD
+        let bin = "6101026000526020602060206000600061beef610ffff1602051";
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut InMemoryDb::new());
+        assert_eq!(result.stack, vec![1.into(), U256::from(0x0201) << 240]);
+    }
+
+    #[test]
+    fn custom_overcharge() {
+        // claims more gas than it was given
+        fn greedy(_input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+            Ok(PrecompileOutput {
+                gas_used: gas_limit + 1,
+                output: vec![1],
+            })
+        }
+
+        let mut env = Env {
+            gas_limit: 100_000,
+            ..Env::default()
+        };
+        env.precompiles
+            .register(Address::from_low_u64_be(0xbeef), greedy);
+
+        // CALL 0xbeef with 0x1000 gas and 32 bytes of output at 0\nRETURNDATASIZE
+        let bin = "6020600060006000600061beef611000f13d";
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut InMemoryDb::new());
+        assert!(result.success);
+        assert_eq!(result.stack, vec![0.into(), 0.into()]);
+
+        // the gas passed on is gone, 7 pushes, the call, one word of memory and RETURNDATASIZE
+        assert_eq!(result.gas_used, 7 * 3 + 100 + 0x1000 + 3 + 2);
+    }
+}
@@ -0,0 +1,209 @@
+/// Ethereum hardforks, in the order they were activated
+///
+/// later forks include every change made by the earlier ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum SpecId {
+    Frontier,
+    Homestead,
+    /// EIP-150 repricing and the 63/64 call gas rule
+    TangerineWhistle,
+    /// EIP-160 `EXP` repricing and the EIP-170 code size limit
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    /// the merge
+    Paris,
+    Shanghai,
+    #[default]
+    Cancun,
+    Prague,
+}
+
+impl SpecId {
+    /// whether the changes made in `fork` apply under this spec
+    pub fn is_enabled_in(self, fork: SpecId) -> bool {
+        self >= fork
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpecId::{self, *};
+    use crate::{execute, Account, Address, Database, Env, EvmResult, HaltReason, InMemoryDb};
+    use primitive_types::U256;
+
+    fn run(bin: &str, spec: SpecId) -> EvmResult {
+        let mut db = InMemoryDb::new();
+        let env = Env {
+            spec,
+            gas_limit: 10_000_000,
+            ..Env::default()
+        };
+        execute(hex::decode(bin).unwrap(), &env, &mut db)
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(Cancun.is_enabled_in(Berlin));
+        assert!(Berlin.is_enabled_in(Berlin));
+        assert!(!Berlin.is_enabled_in(London));
+        assert_eq!(SpecId::default(), Cancun);
+    }
+
+    #[test]
+    fn opcode_availability() {
+        // (asm, bytecode, last fork without it, fork that added it)
+        let cases = [
+            (
+                "DELEGATECALL",
+                "600060006000600060005af4",
+                Frontier,
+                Homestead,
+            ),
+            ("RETURNDATASIZE", "3d", SpuriousDragon, Byzantium),
+            ("REVERT", "60006000fd", SpuriousDragon, Byzantium),
+            (
+                "STATICCALL",
+                "600060006000600060005afa",
+                SpuriousDragon,
+                Byzantium,
+            ),
+            ("SHL", "600160011b", Byzantium, Constantinople),
+            ("SHR", "600160011c", Byzantium, Constantinople),
+            ("SAR", "600160011d", Byzantium, Constantinople),
+            ("CREATE2", "6000600060006000f5", Byzantium, Constantinople),
+            ("EXTCODEHASH", "60003f", Byzantium, Constantinople),
+            ("CHAINID", "46", Petersburg, Istanbul),
+            ("SELFBALANCE", "47", Petersburg, Istanbul),
+            ("BASEFEE", "48", Berlin, London),
+            ("PUSH0", "5f", Paris, Shanghai),
+            ("TLOAD", "60005c", Shanghai, Cancun),
+            ("TSTORE", "600060005d", Shanghai, Cancun),
+            ("MCOPY", "6000600060005e", Shanghai, Cancun),
+            ("BLOBHASH", "600049", Shanghai, Cancun),
+            ("BLOBBASEFEE", "4a", Shanghai, Cancun),
+        ];
+
+        for (asm, bin, before, since) in cases {
+            let result = run(bin, before);
+            assert_eq!(
+                result.reason,
+                HaltReason::InvalidOpcode,
+                "{} in {:?}",
+                asm,
+                before
+            );
+
+            let result = run(bin, since);
+            assert_ne!(
+                result.reason,
+                HaltReason::InvalidOpcode,
+                "{} in {:?}",
+                asm,
+                since
+            );
+        }
+    }
+
+    #[test]
+    fn gas_schedule() {
+        // (asm, bytecode, fork, gas used)
+        let cases = [
+            ("PUSH1 0\nSLOAD", "600054", Frontier, 3 + 50),
+            ("PUSH1 0\nSLOAD", "600054", TangerineWhistle, 3 + 200),
+            ("PUSH1 0\nSLOAD", "600054", Istanbul, 3 + 800),
+            ("PUSH1 0\nBALANCE", "600031", Frontier, 3 + 20),
+            ("PUSH1 0\nBALANCE", "600031", TangerineWhistle, 3 + 400),
+            ("PUSH1 0\nBALANCE", "600031", Istanbul, 3 + 700),
+            ("PUSH1 0\nEXTCODESIZE", "60003b", TangerineWhistle, 3 + 700),
+            ("PUSH1 0\nEXTCODEHASH", "60003f", Constantinople, 3 + 400),
+            ("PUSH1 10\nPUSH1 2\nEXP", "600a60020a", Homestead, 16 + 10),
+            (
+                "PUSH1 10\nPUSH1 2\nEXP",
+                "600a60020a",
+                SpuriousDragon,
+                16 + 50,
+            ),
+            ("PUSH1 0\nSELFDESTRUCT", "6000ff", Frontier, 3),
+            (
+                "PUSH1 0\nSELFDESTRUCT",
+                "6000ff",
+                TangerineWhistle,
+                3 + 5000 + 25000,
+            ),
+            ("PUSH1 0\nSELFDESTRUCT", "6000ff", SpuriousDragon, 3 + 5000),
+        ];
+
+        for (asm, bin, spec, gas_used) in cases {
+            let result = run(bin, spec);
+            assert!(result.success, "{} in {:?}", asm, spec);
+            assert_eq!(result.gas_used, gas_used, "{} in {:?}", asm, spec);
+        }
+    }
+
+    #[test]
+    fn call_gas() {
+        // CALL 0 asking for 0xffffff gas, more than there is
+        let bin = "60006000600060006000600062fffffff1";
+        let mut db = InMemoryDb::new();
+        let env = |spec| Env {
+            spec,
+            gas_limit: 100_000,
+            ..Env::default()
+        };
+
+        // before EIP-150 that runs out of gas, after it the call just gets less
+        let result = execute(hex::decode(bin).unwrap(), &env(Homestead), &mut db);
+        assert_eq!(result.reason, HaltReason::OutOfGas);
+        let result = execute(hex::decode(bin).unwrap(), &env(TangerineWhistle), &mut db);
+        assert_eq!(result.stack, vec![1.into()]);
+    }
+
+    #[test]
+    fn create_rules() {
+        // CODECOPY and CREATE init code: PUSH2 0x6001\nPUSH1 0\nRETURN
+        let too_big = "6006600f600039600660006000f0006160016000f3";
+        assert_ne!(run(too_big, Homestead).stack, vec![U256::zero()]);
+        assert_eq!(run(too_big, SpuriousDragon).stack, vec![U256::zero()]);
+
+        // CODECOPY and CREATE init code: PUSH1 0xef\nPUSH1 0\nMSTORE8\nPUSH1 1\nPUSH1 0\nRETURN
+        let ef = "600a600f600039600a60006000f00060ef60005360016000f3";
+        assert_ne!(run(ef, Berlin).stack, vec![U256::zero()]);
+        assert_eq!(run(ef, London).stack, vec![U256::zero()]);
+
+        // PUSH2 0xc001\nPUSH1 0\nPUSH1 0\nCREATE
+        let initcode = "61c00160006000f0";
+        assert!(run(initcode, Paris).success);
+        assert_eq!(run(initcode, Shanghai).reason, HaltReason::OutOfGas);
+    }
+
+    #[test]
+    fn selfdestruct_rules() {
+        let contract = Address::from_low_u64_be(0xc0de);
+
+        // (fork, whether the contract is deleted, gas refunded)
+        // the 24000 refund before London is capped at half of the 5003 gas used
+        let cases = [(Berlin, true, 2501), (London, true, 0), (Cancun, false, 0)];
+        for (spec, deleted, refund) in cases {
+            // PUSH1 0\nSELFDESTRUCT
+            let mut db = InMemoryDb::new();
+            db.insert_account(
+                contract,
+                Account::new(0.into(), hex::decode("6000ff").unwrap()),
+            );
+            let env = Env {
+                address: contract,
+                spec,
+                ..Env::default()
+            };
+            let result = execute(db.code(contract), &env, &mut db);
+            assert!(result.success);
+            assert_eq!(db.account(contract).is_none(), deleted, "{:?}", spec);
+            assert_eq!(result.gas_refunded, refund, "{:?}", spec);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::HaltReason;
+use primitive_types::U256;
+
+/// the most items the stack can hold
+pub const STACK_LIMIT: usize = 1024;
+
+/// the word stack of a call frame, which reports underflows and overflows instead of panicking
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stack {
+    // allocated for `STACK_LIMIT` items up front so pushing never reallocates
+    data: Vec<U256>,
+}
+
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stack {
+    pub fn new() -> Self {
+        Self {
+            data: Vec::with_capacity(STACK_LIMIT),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// the items from the bottom of the stack up
+    pub fn data(&self) -> &[U256] {
+        &self.data
+    }
+
+    pub fn into_vec(self) -> Vec<U256> {
+        self.data
+    }
+
+    pub fn push(&mut self, value: U256) -> Result<(), HaltReason> {
+        if self.data.len() == STACK_LIMIT {
+            return Err(HaltReason::StackOverflow);
+        }
+        self.data.push(value);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<U256, HaltReason> {
+        self.data.pop().ok_or(HaltReason::StackUnderflow)
+    }
+
+    /// the item `depth` places below the top, which is at depth 0
+    pub fn peek(&self, depth: usize) -> Result<&U256, HaltReason> {
+        let len = self.data.len();
+        if depth >= len {
+            return Err(HaltReason::StackUnderflow);
+        }
+        Ok(&self.data[len - 1 - depth])
+    }
+
+    /// push a copy of the `n`th item from the top, like `DUPn`
+    pub fn dup(&mut self, n: usize) -> Result<(), HaltReason> {
+        let value = *self.peek(n - 1)?;
+        self.push(value)
+    }
+
+    /// exchange the top item with the one `n` places below it, like `SWAPn`
+    pub fn swap(&mut self, n: usize) -> Result<(), HaltReason> {
+        let len = self.data.len();
+        if n >= len {
+            return Err(HaltReason::StackUnderflow);
+        }
+        self.data.swap(len - 1, len - 1 - n);
+        Ok(())
+    }
+
+    /// check that an instruction taking `inputs` items and leaving `outputs` fits the stack
+    pub fn require(&self, inputs: usize, outputs: usize) -> Result<(), HaltReason> {
+        let len = self.data.len();
+        if len < inputs {
+            return Err(HaltReason::StackUnderflow);
+        }
+        if len - inputs + outputs > STACK_LIMIT {
+            return Err(HaltReason::StackOverflow);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Stack, STACK_LIMIT};
+    use crate::{evm, HaltReason};
+    use primitive_types::U256;
+
+    #[test]
+    fn checked() {
+        let mut stack = Stack::new();
+        assert_eq!(stack.pop(), Err(HaltReason::StackUnderflow));
+        assert_eq!(stack.peek(0), Err(HaltReason::StackUnderflow));
+        assert_eq!(stack.dup(1), Err(HaltReason::StackUnderflow));
+
+        for i in 0..3 {
+            stack.push(U256::from(i)).unwrap();
+        }
+        assert_eq!(stack.peek(2), Ok(&U256::zero()));
+        assert_eq!(stack.swap(3), Err(HaltReason::StackUnderflow));
+        stack.swap(2).unwrap();
+        stack.dup(3).unwrap();
+        assert_eq!(stack.data(), [2, 1, 0, 2].map(U256::from));
+
+        while stack.len() < STACK_LIMIT {
+            stack.push(U256::one()).unwrap();
+        }
+        assert_eq!(stack.push(U256::one()), Err(HaltReason::StackOverflow));
+        assert_eq!(stack.dup(1), Err(HaltReason::StackOverflow));
+        assert_eq!(stack.len(), STACK_LIMIT);
+    }
+
+    #[test]
+    fn require() {
+        let mut stack = Stack::new();
+        stack.push(U256::one()).unwrap();
+        assert_eq!(stack.require(1, 1), Ok(()));
+        assert_eq!(stack.require(2, 1), Err(HaltReason::StackUnderflow));
+
+        while stack.len() < STACK_LIMIT {
+            stack.push(U256::one()).unwrap();
+        }
+        assert_eq!(stack.require(2, 1), Ok(()));
+        assert_eq!(stack.require(1, 2), Err(HaltReason::StackOverflow));
+    }
+
+    #[test]
+    fn overflow() {
+        // 1024 pushes fill the stack and one more halts
+        let result = evm([0x5f; STACK_LIMIT]);
+        assert!(result.success);
+        assert_eq!(result.stack.len(), STACK_LIMIT);
+
+        let result = evm([0x5f; STACK_LIMIT + 1]);
+        assert_eq!(result.reason, HaltReason::StackOverflow);
+        assert_eq!(result.pc, STACK_LIMIT);
+
+        // as does a DUP
+        let mut bin = vec![0x5f; STACK_LIMIT];
+        bin.push(0x8f);
+        assert_eq!(evm(bin).reason, HaltReason::StackOverflow);
+
+        // DUP16 with too few items underflows rather than panicking
+        let result = evm([0x5f, 0x8f]);
+        assert_eq!(result.reason, HaltReason::StackUnderflow);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::env::{copy_slice, word_to_address, CallContext};
+use crate::gas::{self, Gas};
+use crate::hash::sha3;
+use crate::journal::Journal;
+use crate::spec::SpecId;
+use crate::stack::Stack;
+use crate::{Address, HaltReason, Memory};
+use primitive_types::{H256, U256};
+use std::collections::HashMap;
+
+// account state opcodes
+
+const BALANCE: u8 = 0x31;
+const EXTCODESIZE: u8 = 0x3b;
+const EXTCODECOPY: u8 = 0x3c;
+const EXTCODEHASH: u8 = 0x3f;
+const SELFBALANCE: u8 = 0x47;
+
+/// an entry in the world state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    /// wei held by the account
+    pub balance: U256,
+    /// transactions sent, or contracts created if it has code
+    pub nonce: u64,
+    pub code: Vec<u8>,
+    /// keccak256 of `code`, kept in sync by `Account::set_code`
+    pub code_hash: H256,
+    pub storage: HashMap<U256, U256>,
+}
+
+impl Default for Account {
+    fn default() -> Self {
+        Self {
+            balance: U256::zero(),
+            nonce: 0,
+            code: Vec::new(),
+            code_hash: code_hash(&[]),
+            storage: HashMap::new(),
+        }
+    }
+}
+
+impl Account {
+    /// an account holding `balance` with `code` deployed
+    pub fn new(balance: U256, code: impl Into<Vec<u8>>) -> Self {
+        let mut account = Self {
+            balance,
+            ..Self::default()
+        };
+        account.set_code(code.into());
+        account
+    }
+
+    pub fn set_code(&mut self, code: Vec<u8>) {
+        self.code_hash = code_hash(&code);
+        self.code = code;
+    }
+
+    /// whether the account has no code, nonce or balance (EIP-161)
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty() && self.nonce == 0 && self.balance.is_zero()
+    }
+}
+
+/// keccak256 of `code`
+pub fn code_hash(code: &[u8]) -> H256 {
+    let mut hash = H256::zero();
+    sha3(code, hash.as_bytes_mut());
+    hash
+}
+
+/// charge the extra cost of touching `address` for the first time in a transaction (EIP-2929)
+pub fn access_account(
+    state: &mut Journal,
+    gas: &mut Gas,
+    address: Address,
+    spec: SpecId,
+) -> Result<(), HaltReason> {
+    if state.access_address(address) && spec.is_enabled_in(SpecId::Berlin) {
+        gas.record_cost(gas::COLD_ACCOUNT_ACCESS - gas::WARM_STORAGE_READ)?;
+    }
+
+    Ok(())
+}
+
+pub fn exec(
+    opcode: u8,
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+    state: &mut Journal,
+    context: &CallContext,
+    spec: SpecId,
+) -> Result<(), HaltReason> {
+    match opcode {
+        BALANCE => {
+            let address = word_to_address(stack.pop()?);
+            access_account(state, gas, address, spec)?;
+            stack.push(state.balance(address))?;
+        }
+        SELFBALANCE => stack.push(state.balance(context.address))?,
+        EXTCODESIZE => {
+            let address = word_to_address(stack.pop()?);
+            access_account(state, gas, address, spec)?;
+            stack.push(state.code(address).len().into())?;
+        }
+        EXTCODECOPY => {
+            let address = word_to_address(stack.pop()?);
+            let dest_offset = stack.pop()?;
+            let offset = stack.pop()?;
+            let size = stack.pop()?;
+
+            access_account(state, gas, address, spec)?;
+            copy_slice(memory, gas, &state.code(address), dest_offset, offset, size)?;
+        }
+        EXTCODEHASH => {
+            let address = word_to_address(stack.pop()?);
+            access_account(state, gas, address, spec)?;
+
+            // accounts that don't exist hash to zero rather than the empty code hash
+            if state.is_empty(address) {
+                stack.push(U256::zero())?;
+            } else {
+                stack.push(U256::from_big_endian(state.code_hash(address).as_bytes()))?;
+            }
+        }
+        _ => unreachable!("Not an account state opcode"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{code_hash, Account};
+    use crate::tests::{run_with_callee, test_env, CALLEE, CONTRACT};
+    use crate::{execute, Address, Database, Env, InMemoryDb, SpecId};
+    use primitive_types::U256;
+
+    const CODE: &str = "602a60005260206000f3";
+
+    // run `bin` in a world where the callee holds 1000 wei and 0xe0a is funded but has no code
+    fn run(bin: &str) -> Vec<U256> {
+        let mut db = InMemoryDb::new();
+        db.set_balance(Address::from_low_u64_be(CONTRACT), 7.into());
+        db.set_balance(Address::from_low_u64_be(CALLEE), 1000.into());
+        db.insert_account(
+            Address::from_low_u64_be(0xe0a),
+            Account::new(5.into(), Vec::new()),
+        );
+
+        let result = run_with_callee(bin, CODE, &test_env(), &mut db);
+        assert!(result.success, "{}", bin);
+        result.stack
+    }
+
+    #[test]
+    fn empty_code_hash() {
+        assert_eq!(Account::default().code_hash, code_hash(&[]),);
+        assert_eq!(
+            hex::encode(code_hash(&[])),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+    }
+
+    #[test]
+    fn balance() {
+        // PUSH2 0xbeef\nBALANCE\nSELFBALANCE\nPUSH1 0\nBALANCE
+        assert_eq!(
+            run("61beef3147600031"),
+            vec![1000.into(), 7.into(), 0.into()]
+        );
+    }
+
+    #[test]
+    fn cold_accounts() {
+        let run = |env: &Env| {
+            let mut db = InMemoryDb::new();
+            // PUSH2 0xbeef\nBALANCE\nPUSH2 0xbeef\nEXTCODESIZE\nADDRESS\nBALANCE
+            execute(hex::decode("61beef3161beef3b3031").unwrap(), env, &mut db).gas_used
+        };
+
+        // the first touch is cold, and the running contract starts warm
+        assert_eq!(run(&Env::default()), 3 + 2600 + 3 + 100 + 2 + 100);
+
+        // unless it's in the access list
+        let env = Env {
+            access_list: vec![(Address::from_low_u64_be(0xbeef), Vec::new())],
+            ..Env::default()
+        };
+        assert_eq!(run(&env), 3 + 100 + 3 + 100 + 2 + 100);
+
+        // before Berlin there's no difference
+        let env = Env {
+            spec: SpecId::Istanbul,
+            ..Env::default()
+        };
+        assert_eq!(run(&env), 3 + 700 + 3 + 700 + 2 + 700);
+    }
+
+    #[test]
+    fn extcodesize() {
+        // PUSH2 0xbeef\nEXTCODESIZE\nPUSH2 0x0e0a\nEXTCODESIZE
+        assert_eq!(run("61beef3b610e0a3b"), vec![10.into(), 0.into()]);
+    }
+
+    #[test]
+    fn extcodecopy() {
+        // PUSH1 32\nPUSH1 0\nPUSH1 0\nPUSH2 0xbeef\nEXTCODECOPY\nPUSH1 0\nMLOAD
+        let mut word = hex::decode(CODE).unwrap();
+        word.resize(32, 0);
+        assert_eq!(
+            run("60206000600061beef3c600051"),
+            vec![U256::from_big_endian(&word)]
+        );
+
+        // reading from an offset pads the rest with zeros
+        // PUSH1 32\nPUSH1 8\nPUSH1 0\nPUSH2 0xbeef\nEXTCODECOPY\nPUSH1 0\nMLOAD
+        let mut word = vec![0x00, 0xf3];
+        word.resize(32, 0);
+        assert_eq!(
+            run("60206008600061beef3c600051"),
+            vec![U256::from_big_endian(&word)]
+        );
+    }
+
+    #[test]
+    fn extcodehash() {
+        let hash = |code: &[u8]| U256::from_big_endian(code_hash(code).as_bytes());
+
+        // PUSH2 0xbeef\nEXTCODEHASH\nPUSH2 0x0e0a\nEXTCODEHASH\nPUSH1 0\nEXTCODEHASH
+        assert_eq!(
+            run("61beef3f610e0a3f60003f"),
+            vec![hash(&hex::decode(CODE).unwrap()), hash(&[]), 0.into()]
+        );
+    }
+}
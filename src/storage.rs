@@ -0,0 +1,419 @@
+use crate::env::CallContext;
+use crate::gas::{self, Gas};
+use crate::journal::Journal;
+use crate::spec::SpecId;
+use crate::stack::Stack;
+use crate::HaltReason;
+
+// storage opcodes
+
+const SLOAD: u8 = 0x54;
+const SSTORE: u8 = 0x55;
+const TLOAD: u8 = 0x5c;
+const TSTORE: u8 = 0x5d;
+
+pub fn exec(
+    opcode: u8,
+    stack: &mut Stack,
+    storage: &mut Journal,
+    gas: &mut Gas,
+    context: &CallContext,
+    spec: SpecId,
+) -> Result<(), HaltReason> {
+    let address = context.address;
+    let berlin = spec.is_enabled_in(SpecId::Berlin);
+    match opcode {
+        SLOAD => {
+            let key = stack.pop()?;
+
+            // the first read of a slot in a transaction costs more (EIP-2929)
+            if storage.access_slot(address, key) && berlin {
+                gas.record_cost(gas::COLD_SLOAD - gas::WARM_STORAGE_READ)?;
+            }
+
+            stack.push(storage.sload(address, key))?;
+        }
+        SSTORE => {
+            if context.is_static {
+                return Err(HaltReason::StateChangeDuringStaticCall);
+            }
+
+            // the call stipend alone can't pay for a write (EIP-2200)
+            if spec.is_enabled_in(SpecId::Istanbul) && gas.remaining() <= gas::CALL_STIPEND {
+                return Err(HaltReason::OutOfGas);
+            }
+
+            let key = stack.pop()?;
+            let value = stack.pop()?;
+
+            // since Berlin a cold slot costs extra, which comes off the cost of a reset
+            if storage.access_slot(address, key) && berlin {
+                gas.record_cost(gas::COLD_SLOAD)?;
+            }
+
+            let original = storage.original(address, key);
+            let current = storage.sload(address, key);
+            gas.record_cost(gas::sstore_cost(original, current, value, spec))?;
+            gas.record_refund(gas::sstore_refund(original, current, value, spec));
+
+            storage.sstore(address, key, value);
+        }
+        TLOAD => {
+            let key = stack.pop()?;
+            stack.push(storage.tload(address, key))?;
+        }
+        TSTORE => {
+            if context.is_static {
+                return Err(HaltReason::StateChangeDuringStaticCall);
+            }
+
+            let key = stack.pop()?;
+            let value = stack.pop()?;
+            storage.tstore(address, key, value);
+        }
+        _ => unreachable!("Not a storage opcode"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{run_test, TestSetup};
+    use crate::{
+        evm_with_storage, execute, Address, Database, Env, HaltReason, InMemoryDb, SpecId,
+    };
+
+    #[test]
+    fn sstore_sload() {
+        let setups = vec![
+            TestSetup::new(
+                "PUSH1 0x2a\nPUSH1 0\nSSTORE\nPUSH1 0\nSLOAD",
+                "602a600055600054",
+                vec!["0x2a"],
+                true,
+            ),
+            TestSetup::new("PUSH1 1\nSLOAD", "600154", vec!["0x0"], true),
+        ];
+
+        for setup in setups {
+            run_test(setup);
+        }
+    }
+
+    #[test]
+    fn persists_between_calls() {
+        let mut storage = InMemoryDb::new();
+        let contract = Address::from_low_u64_be(0xc0de);
+
+        // PUSH1 0x2a\nPUSH1 7\nSSTORE
+        let result = evm_with_storage(
+            hex::decode("602a600755").unwrap(),
+            100_000,
+            contract,
+            &mut storage,
+        );
+        assert!(result.success);
+        assert_eq!(storage.sload(contract, 7.into()), 0x2a.into());
+
+        // PUSH1 7\nSLOAD
+        let result = evm_with_storage(
+            hex::decode("600754").unwrap(),
+            100_000,
+            contract,
+            &mut storage,
+        );
+        assert_eq!(result.stack, vec![0x2a.into()]);
+
+        // other contracts have their own storage
+        let other = Address::from_low_u64_be(0xbeef);
+        let result = evm_with_storage(hex::decode("600754").unwrap(), 100_000, other, &mut storage);
+        assert_eq!(result.stack, vec![0.into()]);
+    }
+
+    #[test]
+    fn sstore_gas() {
+        let mut storage = InMemoryDb::new();
+        let contract = Address::zero();
+
+        // PUSH1 1\nPUSH1 0\nSSTORE
+        let result = evm_with_storage(
+            hex::decode("6001600055").unwrap(),
+            100_000,
+            contract,
+            &mut storage,
+        );
+        assert_eq!(result.gas_used, 3 + 3 + 2100 + 20000);
+
+        // PUSH1 2\nPUSH1 0\nSSTORE, the slot is cold again in a new transaction
+        let result = evm_with_storage(
+            hex::decode("6002600055").unwrap(),
+            100_000,
+            contract,
+            &mut storage,
+        );
+        assert_eq!(result.gas_used, 3 + 3 + 5000);
+
+        // not enough gas to fill the slot
+        let result = evm_with_storage(
+            hex::decode("6001600155").unwrap(),
+            10_000,
+            contract,
+            &mut storage,
+        );
+        assert!(!result.success);
+        assert!(storage.sload(contract, 1.into()).is_zero());
+    }
+
+    #[test]
+    fn cold_slots() {
+        let run = |env: &Env| {
+            let mut storage = InMemoryDb::new();
+            // PUSH1 0\nSLOAD\nPUSH1 0\nSLOAD
+            execute(hex::decode("600054600054").unwrap(), env, &mut storage).gas_used
+        };
+
+        // only the first read of a slot is cold
+        assert_eq!(run(&Env::default()), 3 + 2100 + 3 + 100);
+
+        // unless it's in the access list
+        let env = Env {
+            access_list: vec![(Address::zero(), vec![0.into()])],
+            ..Env::default()
+        };
+        assert_eq!(run(&env), 3 + 100 + 3 + 100);
+
+        // before Berlin every read costs the same
+        let env = Env {
+            spec: SpecId::Istanbul,
+            ..Env::default()
+        };
+        assert_eq!(run(&env), 3 + 800 + 3 + 800);
+    }
+
+    #[test]
+    fn reverted_sub_call_unwarms() {
+        let env = Env {
+            address: Address::from_low_u64_be(0xc0de),
+            gas_limit: 1_000_000,
+            ..Env::default()
+        };
+        let run = |callee: &str| {
+            let mut storage = InMemoryDb::new();
+            storage.set_code(
+                Address::from_low_u64_be(0xbeef),
+                hex::decode(callee).unwrap(),
+            );
+
+            // DELEGATECALL 0xbeef\nGAS\nPUSH1 0\nSLOAD\nPOP\nGAS\nSWAP1\nSUB
+            let bin = "600060006000600061beef5af45a600054505a9003";
+            let result = execute(hex::decode(bin).unwrap(), &env, &mut storage);
+            assert!(result.success);
+            result.stack
+        };
+
+        // PUSH1 0\nSLOAD\nSTOP leaves the slot warm
+        assert_eq!(run("60005400"), vec![1.into(), (3 + 100 + 2 + 2).into()]);
+
+        // PUSH1 0\nSLOAD\nPUSH1 0\nPUSH1 0\nREVERT doesn't
+        assert_eq!(
+            run("60005460006000fd"),
+            vec![0.into(), (3 + 2100 + 2 + 2).into()]
+        );
+    }
+
+    #[test]
+    fn net_gas_metering() {
+        let run = |bin: &str, original: u64, spec: SpecId| {
+            let mut storage = InMemoryDb::new();
+            storage.sstore(Address::zero(), 0.into(), original.into());
+            // the EIP-3529 vectors assume the slot is already warm
+            let env = Env {
+                spec,
+                access_list: vec![(Address::zero(), vec![0.into()])],
+                ..Env::default()
+            };
+            let result = execute(hex::decode(bin).unwrap(), &env, &mut storage);
+            assert!(result.success, "{}", bin);
+            (result.gas_used, result.gas_refunded)
+        };
+
+        // (bytecode, original value, gas used, uncapped refund), from EIP-2200 and EIP-3529
+        let cases = [
+            (
+                SpecId::Istanbul,
+                2,
+                vec![
+                    ("60006000556000600055", 0, 1612, 0),
+                    ("60006000556001600055", 0, 20812, 0),
+                    ("60016000556000600055", 0, 20812, 19200),
+                    ("60016000556002600055", 0, 20812, 0),
+                    ("60016000556001600055", 0, 20812, 0),
+                    ("60006000556000600055", 1, 5812, 15000),
+                    ("60006000556001600055", 1, 5812, 4200),
+                    ("60006000556002600055", 1, 5812, 0),
+                    ("60026000556000600055", 1, 5812, 15000),
+                    ("60026000556003600055", 1, 5812, 0),
+                    ("60026000556001600055", 1, 5812, 4200),
+                    ("60026000556002600055", 1, 5812, 0),
+                    ("60016000556000600055", 1, 5812, 15000),
+                    ("60016000556002600055", 1, 5812, 0),
+                    ("60016000556001600055", 1, 1612, 0),
+                    ("600160005560006000556001600055", 0, 40818, 19200),
+                    ("600060005560016000556000600055", 1, 10818, 19200),
+                ],
+            ),
+            (
+                SpecId::London,
+                5,
+                vec![
+                    ("60006000556000600055", 0, 212, 0),
+                    ("60006000556001600055", 0, 20112, 0),
+                    ("60016000556000600055", 0, 20112, 19900),
+                    ("60016000556002600055", 0, 20112, 0),
+                    ("60016000556001600055", 0, 20112, 0),
+                    ("60006000556000600055", 1, 3012, 4800),
+                    ("60006000556001600055", 1, 3012, 2800),
+                    ("60006000556002600055", 1, 3012, 0),
+                    ("60026000556000600055", 1, 3012, 4800),
+                    ("60026000556003600055", 1, 3012, 0),
+                    ("60026000556001600055", 1, 3012, 2800),
+                    ("60026000556002600055", 1, 3012, 0),
+                    ("60016000556000600055", 1, 3012, 4800),
+                    ("60016000556002600055", 1, 3012, 0),
+                    ("60016000556001600055", 1, 212, 0),
+                    ("600160005560006000556001600055", 0, 40118, 19900),
+                    ("600060005560016000556000600055", 1, 5918, 7600),
+                ],
+            ),
+            // before Istanbul only clearing a slot is refunded
+            (
+                SpecId::Petersburg,
+                2,
+                vec![
+                    ("60006000556000600055", 1, 10012, 15000),
+                    ("60016000556000600055", 0, 25012, 15000),
+                ],
+            ),
+        ];
+        for (spec, quotient, vectors) in cases {
+            for (bin, original, gas_used, refund) in vectors {
+                let expected = (gas_used, u64::min(refund, gas_used / quotient));
+                assert_eq!(run(bin, original, spec), expected, "{} {:?}", bin, spec);
+            }
+        }
+    }
+
+    #[test]
+    fn stipend_sentry() {
+        let run = |gas_limit: u64| {
+            let mut storage = InMemoryDb::new();
+            let env = Env {
+                gas_limit,
+                access_list: vec![(Address::zero(), vec![0.into()])],
+                ..Env::default()
+            };
+            // PUSH1 0\nPUSH1 0\nSSTORE, a warm write that changes nothing
+            execute(hex::decode("6000600055").unwrap(), &env, &mut storage)
+        };
+
+        // with only the stipend left nothing can be written
+        assert_eq!(run(6 + 2300).reason, HaltReason::OutOfGas);
+
+        let result = run(6 + 2301);
+        assert!(result.success);
+        assert_eq!(result.gas_used, 3 + 3 + 100);
+    }
+
+    #[test]
+    fn failed_execution_discards_writes() {
+        let mut storage = InMemoryDb::new();
+        let contract = Address::zero();
+
+        // PUSH1 1\nPUSH1 0\nSSTORE\nPUSH1 0\nPUSH1 0\nREVERT
+        let result = evm_with_storage(
+            hex::decode("600160005560006000fd").unwrap(),
+            100_000,
+            contract,
+            &mut storage,
+        );
+        assert_eq!(result.reason, HaltReason::Revert);
+        assert!(storage.accounts.is_empty());
+
+        // PUSH1 1\nPUSH1 0\nSSTORE\nINVALID
+        let result = evm_with_storage(
+            hex::decode("6001600055fe").unwrap(),
+            100_000,
+            contract,
+            &mut storage,
+        );
+        assert_eq!(result.reason, HaltReason::InvalidOpcode);
+        assert!(storage.accounts.is_empty());
+    }
+
+    #[test]
+    fn tstore_tload() {
+        run_test(TestSetup::new(
+            "PUSH1 0x2a\nPUSH1 0\nTSTORE\nPUSH1 0\nTLOAD",
+            "602a60005d60005c",
+            vec!["0x2a"],
+            true,
+        ));
+
+        let mut storage = InMemoryDb::new();
+        let contract = Address::from_low_u64_be(0xc0de);
+
+        // PUSH1 0x2a\nPUSH1 0\nTSTORE
+        let result = evm_with_storage(
+            hex::decode("602a60005d").unwrap(),
+            100_000,
+            contract,
+            &mut storage,
+        );
+        assert_eq!(result.gas_used, 3 + 3 + 100);
+        assert!(storage.accounts.is_empty());
+
+        // PUSH1 0\nTLOAD, the value is gone in the next transaction
+        let result = evm_with_storage(
+            hex::decode("60005c").unwrap(),
+            100_000,
+            contract,
+            &mut storage,
+        );
+        assert_eq!(result.stack, vec![0.into()]);
+    }
+
+    #[test]
+    fn transient_sub_calls() {
+        let env = Env {
+            address: Address::from_low_u64_be(0xc0de),
+            gas_limit: 1_000_000,
+            ..Env::default()
+        };
+        let run = |bin: &str, callee: &str| {
+            let mut storage = InMemoryDb::new();
+            storage.set_code(
+                Address::from_low_u64_be(0xbeef),
+                hex::decode(callee).unwrap(),
+            );
+            let result = execute(hex::decode(bin).unwrap(), &env, &mut storage);
+            assert!(result.success);
+            result.stack
+        };
+
+        // PUSH1 1\nPUSH1 0\nTSTORE\nDELEGATECALL 0xbeef\nPUSH1 0\nTLOAD
+        let bin = "600160005d600060006000600061beef5af460005c";
+
+        // PUSH1 2\nPUSH1 0\nTSTORE
+        assert_eq!(run(bin, "600260005d"), vec![1.into(), 2.into()]);
+
+        // the same followed by PUSH1 0\nPUSH1 0\nREVERT
+        assert_eq!(run(bin, "600260005d60006000fd"), vec![0.into(), 1.into()]);
+
+        // TSTORE is a state change, so it fails in a STATICCALL
+        // STATICCALL 0xbeef
+        let bin = "600060006000600061beef5afa";
+        assert_eq!(run(bin, "600260005d"), vec![0.into()]);
+        assert_eq!(run(bin, "60005c"), vec![1.into()]);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::env::{word_to_address, CallContext};
+use crate::gas::{self, Gas};
+use crate::journal::Journal;
+use crate::memory::mload_n;
+use crate::spec::SpecId;
+use crate::stack::Stack;
+use crate::{HaltReason, Memory};
+use primitive_types::U256;
+
+// system opcodes
+
+const RETURN: u8 = 0xf3;
+const REVERT: u8 = 0xfd;
+
+pub fn exec(
+    opcode: u8,
+    stack: &mut Stack,
+    memory: &mut Memory,
+    gas: &mut Gas,
+    output: &mut Vec<u8>,
+) -> Result<(), HaltReason> {
+    match opcode {
+        RETURN | REVERT => {
+            let offset = stack.pop()?;
+            let size = stack.pop()?;
+
+            *output = mload_n(memory, gas, offset, size)?.to_vec();
+
+            if opcode == RETURN {
+                Err(HaltReason::Return)
+            } else {
+                Err(HaltReason::Revert)
+            }
+        }
+        _ => unreachable!("Not a system opcode"),
+    }
+}
+
+/// send the running contract's balance to the address on the stack and mark it for deletion
+///
+/// since Cancun (EIP-6780) only contracts created in the same transaction are deleted,
+/// earlier forks delete any contract, and refund gas for it before London (EIP-3529)
+pub fn selfdestruct(
+    stack: &mut Stack,
+    gas: &mut Gas,
+    journal: &mut Journal,
+    context: &CallContext,
+    spec: SpecId,
+) -> Result<(), HaltReason> {
+    if context.is_static {
+        return Err(HaltReason::StateChangeDuringStaticCall);
+    }
+
+    let beneficiary = word_to_address(stack.pop()?);
+    let address = context.address;
+    let balance = journal.balance(address);
+
+    // there's no warm cost here, only the cold one (EIP-2929)
+    if journal.access_address(beneficiary) && spec.is_enabled_in(SpecId::Berlin) {
+        gas.record_cost(gas::COLD_ACCOUNT_ACCESS)?;
+    }
+
+    gas.record_cost(gas::new_account_cost(
+        spec,
+        !balance.is_zero(),
+        journal.is_empty(beneficiary),
+        false,
+    ))?;
+
+    journal.transfer(address, beneficiary, balance);
+
+    if !spec.is_enabled_in(SpecId::Cancun) || journal.is_created(address) {
+        // a contract paying itself burns the balance along with the account
+        journal.set_balance(address, U256::zero());
+        if journal.destroy_account(address) && !spec.is_enabled_in(SpecId::London) {
+            gas.record_refund(gas::SELFDESTRUCT_REFUND);
+        }
+    }
+
+    Err(HaltReason::SelfDestruct)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::env::word_to_address;
+    use crate::{
+        evm, evm_with_gas, execute, transact, Account, Address, Database, Env, EvmResult,
+        HaltReason, InMemoryDb, SpecId,
+    };
+
+    #[test]
+    fn return_data() {
+        // PUSH1 0x2a\nPUSH1 0\nMSTORE\nPUSH1 32\nPUSH1 0\nRETURN
+        let result = evm(hex::decode("602a60005260206000f3").unwrap());
+        assert!(result.success);
+        assert_eq!(result.reason, HaltReason::Return);
+        assert_eq!(
+            result.return_data,
+            hex::decode("000000000000000000000000000000000000000000000000000000000000002a")
+                .unwrap()
+        );
+
+        // PUSH1 0\nPUSH1 0\nRETURN
+        let result = evm(hex::decode("60006000f3").unwrap());
+        assert!(result.success);
+        assert!(result.return_data.is_empty());
+
+        // returning past the end of memory reads zeros
+        // PUSH1 2\nPUSH1 0x40\nRETURN
+        let result = evm(hex::decode("60026040f3").unwrap());
+        assert_eq!(result.return_data, vec![0, 0]);
+    }
+
+    #[test]
+    fn revert() {
+        // revert with `Error("nope")`
+        // PUSH32 0x08c379a0...\nPUSH1 0\nMSTORE
+        // PUSH1 0x20\nPUSH1 4\nMSTORE
+        // PUSH1 4\nPUSH1 0x24\nMSTORE
+        // PUSH32 "nope"\nPUSH1 0x44\nMSTORE
+        // PUSH1 0x64\nPUSH1 0\nREVERT
+        let bin = concat!(
+            "7f08c379a000000000000000000000000000000000000000000000000000000000600052",
+            "6020600452",
+            "6004602452",
+            "7f6e6f706500000000000000000000000000000000000000000000000000000000604452",
+            "60646000fd",
+        );
+        let result = evm_with_gas(hex::decode(bin).unwrap(), 1000);
+        assert!(!result.success);
+        assert_eq!(result.reason, HaltReason::Revert);
+        assert_eq!(
+            result.return_data,
+            hex::decode(concat!(
+                "08c379a0",
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "0000000000000000000000000000000000000000000000000000000000000004",
+                "6e6f706500000000000000000000000000000000000000000000000000000000",
+            ))
+            .unwrap()
+        );
+        // reverting refunds the gas that's left
+        assert!(result.gas_left > 0);
+    }
+
+    #[test]
+    fn failure_has_no_return_data() {
+        // PUSH1 1\nPUSH1 0\nMSTORE8\nINVALID
+        let result = evm(hex::decode("6001600053fe").unwrap());
+        assert!(!result.success);
+        assert!(result.return_data.is_empty());
+    }
+
+    // run `bin` at 0xc0de holding 100 wei with slot 0 set to 1
+    fn destruct(bin: &str, spec: SpecId) -> (EvmResult, InMemoryDb) {
+        let mut db = InMemoryDb::new();
+        let mut account = Account::new(100.into(), hex::decode(bin).unwrap());
+        account.storage.insert(0.into(), 1.into());
+        db.insert_account(Address::from_low_u64_be(0xc0de), account);
+
+        let env = Env {
+            address: Address::from_low_u64_be(0xc0de),
+            spec,
+            ..Env::default()
+        };
+        let result = transact(&env, &mut db);
+        (result, db)
+    }
+
+    #[test]
+    fn selfdestruct() {
+        let contract = Address::from_low_u64_be(0xc0de);
+        let beneficiary = Address::from_low_u64_be(0xbeef);
+
+        // PUSH2 0xbeef\nSELFDESTRUCT
+        let (result, db) = destruct("61beefff", SpecId::Cancun);
+        assert!(result.success);
+        assert_eq!(result.reason, HaltReason::SelfDestruct);
+        assert_eq!(db.balance(beneficiary), 100.into());
+
+        // only the balance moves, the contract itself stays
+        assert!(db.balance(contract).is_zero());
+        assert_eq!(db.code(contract), hex::decode("61beefff").unwrap());
+        assert_eq!(db.sload(contract, 0.into()), 1.into());
+
+        // sending value to a new, cold account costs extra
+        assert_eq!(result.gas_used, 3 + 5000 + 2600 + 25000);
+        assert_eq!(result.gas_refunded, 0);
+    }
+
+    #[test]
+    fn selfdestruct_legacy() {
+        // PUSH2 0xbeef\nSELFDESTRUCT
+        let (result, db) = destruct("61beefff", SpecId::Berlin);
+        assert!(result.success);
+        assert_eq!(db.balance(Address::from_low_u64_be(0xbeef)), 100.into());
+        assert!(db.account(Address::from_low_u64_be(0xc0de)).is_none());
+
+        // the refund is capped at half the gas used
+        assert_eq!(result.gas_used, 3 + 5000 + 2600 + 25000);
+        assert_eq!(result.gas_refunded, result.gas_used / 2);
+
+        // ADDRESS\nSELFDESTRUCT, paying itself burns the balance
+        let (result, db) = destruct("30ff", SpecId::Berlin);
+        assert!(result.success);
+        assert!(db.accounts.is_empty());
+        assert_eq!(result.gas_used, 2 + 5000);
+    }
+
+    #[test]
+    fn selfdestruct_created() {
+        let mut db = InMemoryDb::new();
+        let creator = Address::from_low_u64_be(0xc0de);
+        db.set_balance(creator, 100.into());
+        let env = Env {
+            address: creator,
+            ..Env::default()
+        };
+
+        // init code: PUSH2 0xbeef\nSELFDESTRUCT
+        // CODECOPY the init code\nCREATE with a value of 10
+        let bin = "6004600f60003960046000600af00061beefff";
+        let result = execute(hex::decode(bin).unwrap(), &env, &mut db);
+        assert!(result.success);
+
+        // contracts created in the same transaction are still deleted
+        let created = word_to_address(result.stack[0]);
+        assert!(db.account(created).is_none());
+        assert_eq!(db.balance(creator), 90.into());
+        assert_eq!(db.balance(Address::from_low_u64_be(0xbeef)), 10.into());
+    }
+
+    #[test]
+    fn selfdestruct_static() {
+        let mut db = InMemoryDb::new();
+        db.set_code(
+            Address::from_low_u64_be(0xbeef),
+            hex::decode("6000ff").unwrap(),
+        );
+
+        // STATICCALL 0xbeef which runs PUSH1 0\nSELFDESTRUCT
+        let bin = "600060006000600061beef5afa";
+        let result = execute(hex::decode(bin).unwrap(), &Env::default(), &mut db);
+        assert_eq!(result.stack, vec![0.into()]);
+        assert!(!db.code(Address::from_low_u64_be(0xbeef)).is_empty());
+    }
+}
@@ -1,66 +1,13 @@
-use crate::{PUSH1, PUSH32};
 use primitive_types::U256;
 
-// give a better error message when popping on empty stack
-pub trait Stack<T> {
-    fn safe_pop(&mut self) -> T;
-    fn peek(&self, depth: usize) -> Option<&T>;
-    fn peek_mut(&mut self, depth: usize) -> Option<&mut T>;
-}
-
-impl Stack<U256> for Vec<U256> {
-    fn safe_pop(&mut self) -> U256 {
-        match self.pop() {
-            Some(x) => x,
-            None => panic!("Stack underflow!"),
-        }
-    }
-
-    fn peek(&self, depth: usize) -> Option<&U256> {
-        let len = self.len();
-        if depth > len {
-            return None;
-        } else {
-            self.get(len - depth)
-        }
-    }
-
-    fn peek_mut(&mut self, depth: usize) -> Option<&mut U256> {
-        let len = self.len();
-        if depth > len {
-            return None;
-        } else {
-            self.get_mut(len - depth)
-        }
+// read `size` bytes of `data` starting at `offset`, treating anything past the end as zero
+pub fn padded_slice(data: &[u8], offset: U256, size: usize) -> Vec<u8> {
+    let mut bytes = vec![0; size];
+    if offset < data.len().into() {
+        let offset = offset.as_usize();
+        let end = data.len().min(offset + size);
+        bytes[..end - offset].copy_from_slice(&data[offset..end]);
     }
-}
-
-pub struct ValidJumps {
-    pub jumps: Vec<bool>,
-}
 
-impl ValidJumps {
-    pub fn new(code: &[u8]) -> Self {
-        let mut jumps: Vec<bool> = Vec::with_capacity(code.len());
-
-        let mut i = 0;
-        while i < code.len() {
-            let op = code[i];
-            if op == 0x5b {
-                jumps.push(true);
-            } else if op >= PUSH1 && op <= PUSH32 {
-                let size = (op - PUSH1 + 1) as usize;
-                for _ in 0..size {
-                    jumps.push(false);
-                }
-                i += size;
-                jumps.push(false);
-            } else {
-                jumps.push(false);
-            }
-            i += 1;
-        }
-
-        Self { jumps }
-    }
+    bytes
 }